@@ -0,0 +1,76 @@
+use crate::error::Result;
+use crate::models::*;
+use std::collections::HashMap;
+
+/// A line-delimited stream of controller messages. Ends when the controller
+/// closes the stream.
+pub type Stream<T> = Box<dyn Iterator<Item = Result<T>> + Send>;
+
+/// Everything the commands need from a Clash controller. `ClashClient` talks
+/// to a real controller over HTTP; other implementations can embed the
+/// commands elsewhere or run them against an in-memory fake.
+///
+/// Errors are [`ClashError`](crate::error::ClashError)s so callers can tell the failure kinds apart.
+/// Commands may call the API from several threads at once, hence `Sync`.
+pub trait ClashApi: Sync {
+    fn version(&self) -> Result<Version>;
+
+    fn get_proxies(&self) -> Result<ProxiesResponse>;
+
+    fn get_proxy(&self, name: &str) -> Result<ProxyInfo>;
+
+    fn switch_proxy(&self, group: &str, proxy: &str) -> Result<()>;
+
+    /// Test the latency of a single proxy. Returns `None` when the test
+    /// timed out or the proxy could not reach `url`.
+    fn get_proxy_delay(
+        &self,
+        name: &str,
+        url: &str,
+        timeout: u32,
+        expected: Option<&str>,
+    ) -> Result<Option<u32>>;
+
+    /// Test the latency of every member of a group. Members that timed out
+    /// are missing from the returned map.
+    fn get_group_delay(
+        &self,
+        group: &str,
+        url: &str,
+        timeout: u32,
+        expected: Option<&str>,
+    ) -> Result<HashMap<String, u32>>;
+
+    fn get_connections(&self) -> Result<ConnectionsResponse>;
+
+    fn close_all_connections(&self) -> Result<()>;
+
+    fn close_connection(&self, id: &str) -> Result<()>;
+
+    fn get_rules(&self) -> Result<RulesResponse>;
+
+    fn get_proxy_providers(&self) -> Result<ProxyProvidersResponse>;
+
+    fn get_proxy_provider(&self, name: &str) -> Result<ProxyProvider>;
+
+    /// Ask the controller to re-fetch a provider from its source
+    fn update_proxy_provider(&self, name: &str) -> Result<()>;
+
+    /// Run a latency health check on every proxy of a provider
+    fn healthcheck_proxy_provider(&self, name: &str) -> Result<()>;
+
+    fn get_rule_providers(&self) -> Result<RuleProvidersResponse>;
+
+    /// Ask the controller to re-fetch a rule set from its source
+    fn update_rule_provider(&self, name: &str) -> Result<()>;
+
+    fn get_config(&self) -> Result<Config>;
+
+    fn set_mode(&self, mode: &str) -> Result<()>;
+
+    /// Stream per-second upload/download rates
+    fn stream_traffic(&self) -> Result<Stream<Traffic>>;
+
+    /// Stream log messages at `level` or above
+    fn stream_logs(&self, level: &str) -> Result<Stream<LogEntry>>;
+}
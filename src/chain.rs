@@ -0,0 +1,254 @@
+use crate::models::{ProxyInfo, ProxyType};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// One step of a proxy chain
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChainHop {
+    pub name: String,
+    /// `None` when the name does not exist on the controller
+    #[serde(rename = "type")]
+    pub proxy_type: Option<ProxyType>,
+    /// Last recorded latency in milliseconds
+    pub delay: Option<u32>,
+}
+
+/// Why a chain stopped before reaching a node
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", content = "name", rename_all = "lowercase")]
+pub enum ChainProblem {
+    /// The named group was already visited
+    Cycle(String),
+    /// A group selects a name that does not exist
+    Missing(String),
+}
+
+/// The groups followed from a starting group to the node that carries the
+/// traffic
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct Chain {
+    pub hops: Vec<ChainHop>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<ChainProblem>,
+}
+
+impl Chain {
+    /// Follow the `now` selection from `start` through groups until reaching
+    /// a node, a group without a selection (LoadBalance, Relay), a missing
+    /// name or a group that was already visited.
+    pub fn resolve(proxies: &HashMap<String, ProxyInfo>, start: &str) -> Self {
+        let mut chain = Chain::default();
+        let mut visited = HashSet::new();
+        let mut name = start.to_string();
+
+        loop {
+            let Some(info) = proxies.get(&name) else {
+                chain.hops.push(ChainHop {
+                    name: name.clone(),
+                    proxy_type: None,
+                    delay: None,
+                });
+                chain.problem = Some(ChainProblem::Missing(name));
+                break;
+            };
+
+            chain.hops.push(ChainHop {
+                name: name.clone(),
+                proxy_type: Some(info.proxy_type.clone()),
+                delay: info
+                    .history
+                    .last()
+                    .map(|item| item.delay)
+                    .filter(|d| *d > 0),
+            });
+            visited.insert(name);
+
+            if !info.proxy_type.is_group() || info.now.is_empty() {
+                break;
+            }
+            if visited.contains(&info.now) {
+                chain.problem = Some(ChainProblem::Cycle(info.now.clone()));
+                break;
+            }
+            name = info.now.clone();
+        }
+
+        chain
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.hops.iter().map(|hop| hop.name.as_str()).collect()
+    }
+
+    /// The last hop, normally the node carrying the traffic
+    pub fn node(&self) -> Option<&ChainHop> {
+        self.hops.last()
+    }
+
+    /// The group that selected the last hop
+    pub fn group(&self) -> Option<&ChainHop> {
+        self.hops.iter().rev().nth(1)
+    }
+
+    /// Print the chain as a tree, one hop per line, followed by any problem.
+    /// `active_label` marks the last hop as the active node.
+    pub fn print_tree(&self, active_label: bool) {
+        let last = self.hops.len().saturating_sub(1);
+        for (i, hop) in self.hops.iter().enumerate() {
+            let delay = hop
+                .delay
+                .map(|d| format!(" {}ms", d).bright_black().to_string())
+                .unwrap_or_default();
+
+            if i < last {
+                let branch = if i == 0 { "┌─" } else { "├─→" };
+                println!(
+                    "  {} {}{}",
+                    branch.bright_blue(),
+                    hop.name.bright_cyan(),
+                    delay
+                );
+                continue;
+            }
+
+            let branch = if self.hops.len() == 1 {
+                "──→"
+            } else {
+                "└─→"
+            };
+            let label = if active_label && self.problem.is_none() {
+                " (active)".bright_black().to_string()
+            } else {
+                String::new()
+            };
+            println!(
+                "  {} {}{}{}",
+                branch.bright_green(),
+                hop.name.bright_green().bold(),
+                delay,
+                label
+            );
+        }
+
+        match &self.problem {
+            Some(ChainProblem::Cycle(name)) => println!(
+                "  {} '{}' selects '{}', which is already in the chain",
+                "⚠ Cycle:".red().bold(),
+                self.node().map(|hop| hop.name.as_str()).unwrap_or_default(),
+                name
+            ),
+            Some(ChainProblem::Missing(name)) => println!(
+                "  {} '{}' does not exist on the controller",
+                "⚠ Missing:".red().bold(),
+                name
+            ),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HistoryItem;
+
+    fn proxy(proxy_type: &str, now: &str, delay: Option<u32>) -> ProxyInfo {
+        ProxyInfo {
+            proxy_type: ProxyType::from(proxy_type.to_string()),
+            all: if now.is_empty() {
+                Vec::new()
+            } else {
+                vec![now.to_string()]
+            },
+            now: now.to_string(),
+            history: delay
+                .map(|delay| HistoryItem {
+                    time: String::new(),
+                    delay,
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn proxies<const N: usize>(entries: [(&str, ProxyInfo); N]) -> HashMap<String, ProxyInfo> {
+        entries
+            .into_iter()
+            .map(|(name, info)| (name.to_string(), info))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_through_groups() {
+        let proxies = proxies([
+            ("GLOBAL", proxy("Selector", "Proxy", None)),
+            ("Proxy", proxy("Selector", "Auto", None)),
+            ("Auto", proxy("Fallback", "HK 01", None)),
+            ("HK 01", proxy("Shadowsocks", "", Some(80))),
+        ]);
+
+        let chain = Chain::resolve(&proxies, "GLOBAL");
+        assert_eq!(chain.names(), vec!["GLOBAL", "Proxy", "Auto", "HK 01"]);
+        assert_eq!(chain.problem, None);
+        assert_eq!(chain.node().unwrap().delay, Some(80));
+        assert_eq!(chain.group().unwrap().name, "Auto");
+
+        // Any group can be the start
+        assert_eq!(
+            Chain::resolve(&proxies, "Auto").names(),
+            vec!["Auto", "HK 01"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_cycle() {
+        let proxies = proxies([
+            ("GLOBAL", proxy("Selector", "A", None)),
+            ("A", proxy("Selector", "B", None)),
+            ("B", proxy("Selector", "A", None)),
+            ("Self", proxy("Selector", "Self", None)),
+        ]);
+
+        let chain = Chain::resolve(&proxies, "GLOBAL");
+        assert_eq!(chain.names(), vec!["GLOBAL", "A", "B"]);
+        assert_eq!(chain.problem, Some(ChainProblem::Cycle("A".to_string())));
+
+        let chain = Chain::resolve(&proxies, "Self");
+        assert_eq!(chain.names(), vec!["Self"]);
+        assert_eq!(chain.problem, Some(ChainProblem::Cycle("Self".to_string())));
+    }
+
+    #[test]
+    fn test_resolve_missing() {
+        let proxies = proxies([("GLOBAL", proxy("Selector", "Gone", None))]);
+
+        let chain = Chain::resolve(&proxies, "GLOBAL");
+        assert_eq!(chain.names(), vec!["GLOBAL", "Gone"]);
+        assert_eq!(chain.node().unwrap().proxy_type, None);
+        assert_eq!(
+            chain.problem,
+            Some(ChainProblem::Missing("Gone".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_chain_serialize() {
+        let proxies = proxies([
+            ("LB", proxy("LoadBalance", "", None)),
+            ("X", proxy("Selector", "Y", None)),
+        ]);
+
+        let chain = Chain::resolve(&proxies, "LB");
+        assert_eq!(
+            serde_json::to_string(&chain).unwrap(),
+            r#"{"hops":[{"name":"LB","type":"LoadBalance","delay":null}]}"#
+        );
+
+        let chain = Chain::resolve(&proxies, "X");
+        assert_eq!(
+            serde_json::to_value(&chain).unwrap()["problem"],
+            serde_json::json!({"kind": "missing", "name": "Y"})
+        );
+    }
+}
@@ -1,20 +1,126 @@
-use clap::{Parser, Subcommand};
+use crate::output::OutputFormat;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
-    /// Clash API URL
-    #[arg(long, default_value = "http://localhost:9090", global = true)]
-    pub url: String,
+    /// Clash API URL, or unix:///path/to/socket [default: http://localhost:9090]
+    #[arg(long, env = "CLASH_URL", global = true)]
+    pub url: Option<String>,
 
-    /// Clash API secret (if configured)
-    #[arg(long, default_value = "", global = true)]
-    pub secret: String,
+    /// Clash API secret (if configured). Prefer CLASH_SECRET or --secret-file,
+    /// command-line arguments are visible to other users
+    #[arg(long, env = "CLASH_SECRET", hide_env_values = true, global = true)]
+    pub secret: Option<String>,
+
+    /// Read the API secret from a file (must not be group/world-readable)
+    #[arg(long, env = "CLASH_SECRET_FILE", global = true)]
+    pub secret_file: Option<PathBuf>,
+
+    /// Read the API secret from the first line of standard input (takes
+    /// precedence over --secret-file and --secret)
+    #[arg(long, global = true)]
+    pub secret_stdin: bool,
+
+    /// Controller profile from the config file
+    #[arg(long, env = "CLASH_SWITCHER_PROFILE", global = true)]
+    pub profile: Option<String>,
+
+    /// Config file [default: ~/.config/clash-switcher/config.toml]
+    #[arg(long, env = "CLASH_SWITCHER_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Controller connect timeout in milliseconds [default: 3000]
+    #[arg(long, value_name = "MS", global = true)]
+    pub connect_timeout: Option<u64>,
+
+    /// Controller response timeout in milliseconds [default: 10000]
+    #[arg(long, value_name = "MS", global = true)]
+    pub read_timeout: Option<u64>,
+
+    /// Idle timeout of the traffic stream in milliseconds [default: 300000]
+    #[arg(long, value_name = "MS", global = true)]
+    pub stream_timeout: Option<u64>,
+
+    /// Retries for GET requests that got no response [default: 2]
+    #[arg(long, global = true)]
+    pub retries: Option<u32>,
+
+    /// Wait before the first retry in milliseconds, doubled for each retry
+    /// [default: 200]
+    #[arg(long, value_name = "MS", global = true)]
+    pub retry_backoff: Option<u64>,
+
+    /// PEM file with the CA that signed an HTTPS controller's certificate
+    #[arg(long, env = "CLASH_CA_CERT", global = true)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Accept any certificate from an HTTPS controller
+    #[arg(long, global = true, conflicts_with = "pin_sha256")]
+    pub insecure: bool,
+
+    /// Accept only the HTTPS controller certificate with this SHA-256
+    /// fingerprint (hex, colons optional). Can be repeated.
+    #[arg(long, value_name = "FINGERPRINT", global = true)]
+    pub pin_sha256: Vec<String>,
+
+    /// Output format (json and plain are supported by version, proxies,
+    /// proxy, current, switch and mode)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table, global = true)]
+    pub output: OutputFormat,
 
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// Where the API secret given to this invocation comes from
+#[derive(Debug, PartialEq)]
+pub enum SecretSource {
+    Stdin,
+    File(PathBuf),
+    /// Given with --secret
+    Value(String),
+    /// Taken from CLASH_SECRET
+    Env(String),
+}
+
+impl Cli {
+    /// Pick the secret source: --secret-stdin, then an explicit --secret-file
+    /// or --secret flag, then CLASH_SECRET_FILE or CLASH_SECRET. `matches`
+    /// tells flags apart from environment variables.
+    pub fn secret_source(&self, matches: &ArgMatches) -> Option<SecretSource> {
+        let explicit = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let file = || self.secret_file.clone().map(SecretSource::File);
+
+        if self.secret_stdin {
+            Some(SecretSource::Stdin)
+        } else if explicit("secret_file") {
+            file()
+        } else if explicit("secret") {
+            self.secret.clone().map(SecretSource::Value)
+        } else {
+            file().or_else(|| self.secret.clone().map(SecretSource::Env))
+        }
+    }
+}
+
+impl Command {
+    /// Whether the command can print `--output json|plain`
+    pub fn supports_output(&self) -> bool {
+        matches!(
+            self,
+            Command::Version
+                | Command::Proxies
+                | Command::Proxy { .. }
+                | Command::Current { .. }
+                | Command::Switch { .. }
+                | Command::Mode { .. }
+        )
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// Show Clash version
@@ -27,13 +133,165 @@ pub enum Command {
         name: String,
     },
     /// Show current proxy chain from GLOBAL selector
-    Current,
+    Current {
+        /// Show the chain starting from this group instead (name, prefix or number)
+        group: Option<String>,
+        /// Show the chain of every top-level group and how many rules use it
+        #[arg(long, conflicts_with = "group")]
+        all: bool,
+    },
+    /// Print a one-line status for shell prompts and status bars
+    Status {
+        /// Template with {mode} {group} {node} {type} {chain} {delay} {up} {down}
+        #[arg(long, default_value = crate::commands::status::DEFAULT_TEMPLATE)]
+        format: String,
+        /// Print a new line every --interval seconds
+        #[arg(long)]
+        watch: bool,
+        /// Seconds between updates in watch mode
+        #[arg(long, default_value_t = 2, requires = "watch")]
+        interval: u64,
+        /// Emit waybar custom module JSON ({text, tooltip, class})
+        #[arg(long)]
+        waybar: bool,
+    },
+    /// List active connections, optionally closing the matched ones
+    Connections {
+        /// Only show connections whose host or destination IP contains this
+        #[arg(long)]
+        host: Option<String>,
+        /// Only show connections whose chain passes through this proxy or group
+        #[arg(long)]
+        chain: Option<String>,
+        /// Only show connections whose rule type or payload contains this
+        #[arg(long)]
+        rule: Option<String>,
+        /// Close all matched connections
+        #[arg(long)]
+        close: bool,
+    },
+    /// Test latency of a proxy node or every member of a group
+    Delay {
+        /// Proxy or group name (exact or prefix) [default: profile group]
+        name: Option<String>,
+        /// URL used for the latency test [default: https://www.gstatic.com/generate_204]
+        #[arg(long)]
+        test_url: Option<String>,
+        /// Test timeout in milliseconds [default: 5000]
+        #[arg(long)]
+        timeout: Option<u32>,
+        /// Expected HTTP status code(s), e.g. 204 or 200-299
+        #[arg(long)]
+        expected: Option<String>,
+    },
+    /// Manage controller profiles in the config file
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommand,
+    },
+    /// Manage proxy providers (subscriptions)
+    Providers {
+        #[command(subcommand)]
+        action: ProvidersCommand,
+    },
+    /// Manage rule providers (remote rule sets)
+    RuleProviders {
+        #[command(subcommand)]
+        action: RuleProvidersCommand,
+    },
+    /// Explain which rule and proxy chain a host or URL would use
+    Route {
+        /// Host, host:port or URL (e.g., https://example.com)
+        target: String,
+    },
+    /// List routing rules
+    Rules {
+        /// Only show rules of this type (e.g., DomainSuffix or DOMAIN-SUFFIX)
+        #[arg(long = "type")]
+        rule_type: Option<String>,
+        /// Only show rules whose payload contains this
+        #[arg(long)]
+        payload: Option<String>,
+        /// Only show rules routed to this proxy or group
+        #[arg(long)]
+        target: Option<String>,
+        /// Show the number of rules per target instead of the rules
+        #[arg(long)]
+        count: bool,
+    },
     /// Switch proxy selection (interactive if no args provided)
     Switch {
-        /// Proxy group name (e.g., GLOBAL)
+        /// Proxy group name (e.g., GLOBAL) [default: profile group]
         group: Option<String>,
         /// Target proxy name (e.g., DIRECT)
         proxy: Option<String>,
+        /// Close connections routed through the group after switching
+        #[arg(
+            long,
+            env = "CLASH_SWITCHER_CLOSE_CONNECTIONS",
+            value_parser = clap::builder::BoolishValueParser::new(),
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "true"
+        )]
+        close_connections: Option<bool>,
+        /// Delay-test every member of the group and switch to the fastest one
+        #[arg(long, conflicts_with = "proxy")]
+        fastest: bool,
+        /// Skip members whose name contains this pattern (case-insensitive)
+        #[arg(long, requires = "fastest")]
+        exclude: Option<String>,
+        /// Keep the current node if it is within this many milliseconds of the fastest
+        #[arg(long, default_value_t = 20, requires = "fastest")]
+        tolerance: u32,
+        /// Number of concurrent latency tests
+        #[arg(long, default_value_t = 8, requires = "fastest")]
+        workers: usize,
+        /// URL used for the latency test [default: https://www.gstatic.com/generate_204]
+        #[arg(long, requires = "fastest")]
+        test_url: Option<String>,
+        /// Test timeout in milliseconds [default: 5000]
+        #[arg(long, requires = "fastest")]
+        timeout: Option<u32>,
+    },
+    /// Show live upload/download rates
+    Traffic {
+        /// Print a single sample and exit
+        #[arg(long, conflicts_with = "duration")]
+        once: bool,
+        /// Sample for this many seconds, then print a summary and exit
+        #[arg(long)]
+        duration: Option<u64>,
+    },
+    /// Stream controller logs
+    Logs {
+        /// Minimum log level to receive
+        #[arg(long, value_enum, default_value_t = LogLevel::Info)]
+        level: LogLevel,
+        /// Only show messages containing this substring (case-insensitive)
+        #[arg(long)]
+        grep: Option<String>,
+        /// Only show messages matching this regular expression
+        #[arg(long)]
+        regex: Option<String>,
+        /// Print raw JSON messages instead of colored text
+        #[arg(long)]
+        json: bool,
+        /// Exit when the stream ends instead of reconnecting
+        #[arg(long)]
+        no_reconnect: bool,
+    },
+    /// Browse groups and switch proxies in a full-screen terminal UI
+    Tui {
+        /// URL used for latency tests [default: https://www.gstatic.com/generate_204]
+        #[arg(long)]
+        test_url: Option<String>,
+        /// Test timeout in milliseconds [default: 5000]
+        #[arg(long)]
+        timeout: Option<u32>,
+        /// Seconds between reloads of the proxy state
+        #[arg(long, default_value_t = 5)]
+        refresh: u64,
     },
     /// Set Clash mode (Global/Rule/Direct)
     Mode {
@@ -41,3 +299,135 @@ pub enum Command {
         mode: Option<String>,
     },
 }
+
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommand {
+    /// Add or replace a profile, taking the controller from --url and --secret
+    Add {
+        /// Profile name
+        name: String,
+        /// Group used by switch and delay when none is given
+        #[arg(long)]
+        group: Option<String>,
+        /// Latency test timeout in milliseconds
+        #[arg(long)]
+        timeout: Option<u32>,
+        /// Latency test URL
+        #[arg(long)]
+        test_url: Option<String>,
+        /// Close connections through the group after switching
+        #[arg(long)]
+        close_connections: bool,
+        /// Make this the default profile
+        #[arg(long)]
+        default: bool,
+    },
+    /// List profiles
+    List,
+    /// Remove a profile
+    Remove {
+        /// Profile name
+        name: String,
+    },
+    /// Set the profile used when --profile is not given
+    SetDefault {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProvidersCommand {
+    /// List proxy providers
+    List,
+    /// Show the proxies of a provider
+    Show {
+        /// Provider name
+        name: String,
+    },
+    /// Re-fetch providers from their source
+    Update {
+        /// Provider name
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+        /// Update every provider
+        #[arg(long)]
+        all: bool,
+    },
+    /// Run a latency health check on a provider's proxies
+    Healthcheck {
+        /// Provider name
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+        /// Check every provider
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RuleProvidersCommand {
+    /// List rule providers
+    List,
+    /// Re-fetch rule providers from their source
+    Update {
+        /// Provider name
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+        /// Update every provider
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Silent,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warning => "warning",
+            LogLevel::Error => "error",
+            LogLevel::Silent => "silent",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn source(args: &[&str]) -> Option<SecretSource> {
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        Cli::from_arg_matches(&matches)
+            .unwrap()
+            .secret_source(&matches)
+    }
+
+    #[test]
+    fn test_secret_source_precedence() {
+        assert_eq!(
+            source(&["cs", "--secret", "abc", "version"]),
+            Some(SecretSource::Value("abc".to_string()))
+        );
+        assert_eq!(
+            source(&["cs", "--secret", "abc", "--secret-file", "/s", "version"]),
+            Some(SecretSource::File(PathBuf::from("/s")))
+        );
+        assert_eq!(source(&["cs", "version"]), None);
+        // --secret-stdin no longer conflicts with a secret file
+        assert_eq!(
+            source(&["cs", "version", "--secret-stdin", "--secret-file", "/s"]),
+            Some(SecretSource::Stdin)
+        );
+    }
+}
@@ -1,15 +1,146 @@
+use crate::api::{ClashApi, Stream};
+use crate::error::{ClashError, Result};
 use crate::models::*;
-use anyhow::{Context, ensure};
-use reqwest::blocking::Client;
+use crate::tls::{self, TlsOptions};
+use reqwest::blocking::{Client, RequestBuilder, Response};
 use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use reqwest::{Method, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// Timeouts and retry policy of a `ClashClient`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientOptions {
+    pub connect_timeout: Duration,
+    /// Longest wait for a response or for the next chunk of its body
+    pub read_timeout: Duration,
+    /// Longest silence tolerated on the `/traffic` stream, which sends a
+    /// sample every second. `/logs` may stay quiet indefinitely and has no
+    /// idle timeout.
+    pub stream_timeout: Duration,
+    /// Extra attempts for GET requests that got no response
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each further retry
+    pub retry_backoff: Duration,
+    pub tls: TlsOptions,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(3),
+            read_timeout: Duration::from_secs(10),
+            stream_timeout: Duration::from_secs(300),
+            retries: 2,
+            retry_backoff: Duration::from_millis(200),
+            tls: TlsOptions::default(),
+        }
+    }
+}
+
+impl ClientOptions {
+    /// Wait before retry number `attempt` (0-based)
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.retry_backoff.saturating_mul(1 << attempt.min(16))
+    }
+}
+
+/// Controller URLs of the form `unix:///path/to/clash.sock` name a Unix
+/// domain socket, such as mihomo's `external-controller-unix`
+const UNIX_SCHEME: &str = "unix://";
 
 pub struct ClashClient {
     base_url: String,
+    /// Socket path when `base_url` uses `unix://`
+    socket: Option<PathBuf>,
+    options: ClientOptions,
     client: Client,
+    /// Client with the stream idle timeout, for `/traffic`
+    stream_client: Client,
+    /// Client without a timeout, for `/logs`
+    log_client: Client,
+}
+
+/// Iterator over a line-delimited JSON stream such as `/traffic` or `/logs`.
+/// Ends when the controller closes the stream.
+pub struct JsonLines<T> {
+    reader: BufReader<Response>,
+    line: String,
+    base_url: String,
+    _marker: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> Iterator for JsonLines<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) if self.line.trim().is_empty() => continue,
+                Ok(_) => {
+                    return Some(serde_json::from_str(self.line.trim()).map_err(|source| {
+                        ClashError::Decode {
+                            what: "stream message".to_string(),
+                            source,
+                        }
+                    }));
+                }
+                Err(e) => {
+                    return Some(Err(ClashError::Unreachable {
+                        url: self.base_url.clone(),
+                        source: e.into(),
+                    }));
+                }
+            }
+        }
+    }
+}
+
+/// Fail with `InvalidRequest` when a required argument is empty
+fn require(value: &str, what: &str) -> Result<()> {
+    if value.is_empty() {
+        return Err(ClashError::InvalidRequest(format!("{} is empty", what)));
+    }
+    Ok(())
+}
+
+/// The `message` of a controller error body, or the raw body
+fn error_message(response: Response) -> String {
+    let body = response.text().unwrap_or_default();
+    serde_json::from_str::<ErrorResponse>(&body)
+        .map(|e| e.message)
+        .unwrap_or(body)
+}
+
+/// Turn a non-success response into the matching error. `subject` names the
+/// requested resource in `NotFound`.
+fn check(response: Response, subject: &str) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    Err(match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ClashError::Unauthorized(status),
+        StatusCode::NOT_FOUND => ClashError::NotFound(subject.to_string()),
+        _ => ClashError::Http {
+            status,
+            body: error_message(response),
+        },
+    })
 }
 
 impl ClashClient {
-    pub fn new(base_url: String, secret: Option<String>) -> Self {
+    /// Fails with `InvalidRequest` when the TLS options are unusable, e.g. a
+    /// missing CA file or a malformed pin
+    pub fn new(base_url: String, secret: Option<String>, options: ClientOptions) -> Result<Self> {
         let mut headers = HeaderMap::new();
 
         if let Some(secret) = secret
@@ -19,112 +150,446 @@ impl ClashClient {
             headers.insert(AUTHORIZATION, value);
         }
 
-        let client = Client::builder()
-            .default_headers(headers)
-            .build()
-            .expect("Failed to build HTTP client");
-
-        Self { base_url, client }
-    }
-
-    pub fn version(&self) -> anyhow::Result<Version> {
-        ensure!(!self.base_url.is_empty());
-        self.client
-            .get(format!("{}/version", self.base_url))
-            .send()
-            .context("Failed to get the clash version due to the request")?
-            .json::<Version>()
-            .context("Failed to parse clash version")
-    }
-
-    pub fn get_proxies(&self) -> anyhow::Result<ProxiesResponse> {
-        ensure!(!self.base_url.is_empty(), "Base URL is empty");
-        self.client
-            .get(format!("{}/proxies", self.base_url))
-            .send()
-            .context("Failed to get proxies")?
-            .json::<ProxiesResponse>()
-            .context("Failed to parse proxies response")
-    }
-
-    pub fn get_proxy(&self, name: &str) -> anyhow::Result<ProxyInfo> {
-        ensure!(!self.base_url.is_empty(), "Base URL is empty");
-        ensure!(!name.is_empty(), "Proxy name is empty");
-        self.client
-            .get(format!("{}/proxies/{}", self.base_url, name))
-            .send()
-            .context("Failed to get proxy info")?
-            .json::<ProxyInfo>()
-            .context("Failed to parse proxy info")
-    }
-
-    pub fn switch_proxy(&self, group: &str, proxy: &str) -> anyhow::Result<()> {
-        ensure!(!self.base_url.is_empty(), "Base URL is empty");
-        ensure!(!group.is_empty(), "Proxy group name is empty");
-        ensure!(!proxy.is_empty(), "Proxy name is empty");
-
-        let response = self
-            .client
-            .put(format!("{}/proxies/{}", self.base_url, group))
-            .json(&SwitchRequest {
-                name: proxy.to_string(),
+        let socket = base_url.strip_prefix(UNIX_SCHEME).map(PathBuf::from);
+
+        // The blocking client applies `timeout` to each wait: the response
+        // headers and every read of the body
+        let build = |timeout: Option<Duration>| {
+            let builder = Client::builder()
+                .default_headers(headers.clone())
+                .connect_timeout(options.connect_timeout)
+                .timeout(timeout);
+            #[cfg(unix)]
+            let builder = match &socket {
+                Some(path) => builder.unix_socket(path.clone()),
+                None => builder,
+            };
+            options.tls.apply(builder)?.build().map_err(|e| {
+                ClashError::InvalidRequest(format!("Failed to build HTTP client: {}", e))
             })
-            .send()
-            .context("Failed to switch proxy")?;
+        };
+
+        let client = build(Some(options.read_timeout))?;
+        let stream_client = build(Some(options.stream_timeout))?;
+        let log_client = build(None)?;
+
+        Ok(Self {
+            base_url,
+            socket,
+            options,
+            client,
+            stream_client,
+            log_client,
+        })
+    }
+
+    /// Build an endpoint URL, percent-encoding each of `segments` as a single
+    /// path segment so names containing `/`, `#`, `?` or spaces stay intact
+    fn endpoint(&self, segments: &[&str]) -> Result<Url> {
+        require(&self.base_url, "Base URL")?;
+
+        let base = match &self.socket {
+            Some(path) => {
+                require(&path.to_string_lossy(), "Socket path")?;
+                if cfg!(not(unix)) {
+                    return Err(ClashError::InvalidRequest(
+                        "Unix socket controllers are not supported on this platform".to_string(),
+                    ));
+                }
+                // Requests go to the socket; the URL only supplies the path
+                "http://localhost"
+            }
+            None => self.base_url.as_str(),
+        };
 
-        if response.status().is_success() {
-            Ok(())
+        let invalid =
+            || ClashError::InvalidRequest(format!("Invalid controller URL '{}'", self.base_url));
+        let mut url = Url::parse(base).map_err(|_| invalid())?;
+        url.path_segments_mut()
+            .map_err(|_| invalid())?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    fn unreachable(&self, source: reqwest::Error) -> ClashError {
+        if let Some(reason) = tls::certificate_error(&source) {
+            return ClashError::Certificate {
+                url: self.base_url.clone(),
+                reason,
+            };
+        }
+        ClashError::Unreachable {
+            url: self.base_url.clone(),
+            source: source.into(),
+        }
+    }
+
+    /// Send a request; no response at all means the controller is unreachable.
+    /// GET requests are retried with exponential backoff, others are sent
+    /// once since they may have reached the controller.
+    fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request.map_err(|e| ClashError::InvalidRequest(e.to_string()))?;
+        let retries = if request.method() == Method::GET {
+            self.options.retries
         } else {
-            anyhow::bail!(
-                "Failed to switch proxy: {} - {}",
-                response.status(),
-                response.text().unwrap_or_default()
-            )
+            0
+        };
+
+        let mut attempt = 0;
+        loop {
+            let copy = match request.try_clone() {
+                Some(copy) if attempt < retries => copy,
+                _ => return client.execute(request).map_err(|e| self.unreachable(e)),
+            };
+            match client.execute(copy) {
+                Ok(response) => return Ok(response),
+                // The same certificate would be rejected again
+                Err(e) if tls::certificate_error(&e).is_some() => {
+                    return Err(self.unreachable(e));
+                }
+                Err(_) => {
+                    thread::sleep(self.options.backoff(attempt));
+                    attempt += 1;
+                }
+            }
         }
     }
 
-    pub fn get_config(&self) -> anyhow::Result<Config> {
-        ensure!(!self.base_url.is_empty(), "Base URL is empty");
-        self.client
-            .get(format!("{}/configs", self.base_url))
-            .send()
-            .context("Failed to get config")?
-            .json::<Config>()
-            .context("Failed to parse config")
+    /// Per-request timeout for latency tests, which take up to `timeout` ms
+    /// before the controller answers
+    fn delay_timeout(&self, timeout: u32) -> Duration {
+        self.options.read_timeout + Duration::from_millis(timeout.into())
     }
 
-    pub fn set_mode(&self, mode: &str) -> anyhow::Result<()> {
-        ensure!(!self.base_url.is_empty(), "Base URL is empty");
-        ensure!(!mode.is_empty(), "Mode is empty");
+    /// Check the status of a response and parse its JSON body
+    fn json<T: DeserializeOwned>(&self, response: Response, subject: &str) -> Result<T> {
+        let body = check(response, subject)?
+            .text()
+            .map_err(|e| self.unreachable(e))?;
+        serde_json::from_str(&body).map_err(|source| ClashError::Decode {
+            what: subject.to_string(),
+            source,
+        })
+    }
 
-        let response = self
-            .client
-            .patch(format!("{}/configs", self.base_url))
-            .json(&ModeRequest {
-                mode: mode.to_string(),
-            })
-            .send()
-            .context("Failed to set mode")?;
+    fn get_json<T: DeserializeOwned>(&self, segments: &[&str], subject: &str) -> Result<T> {
+        let response = self.send(self.client.get(self.endpoint(segments)?))?;
+        self.json(response, subject)
+    }
 
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            anyhow::bail!(
-                "Failed to set mode: {} - {}",
-                response.status(),
-                response.text().unwrap_or_default()
-            )
+    fn stream<T: DeserializeOwned>(
+        &self,
+        client: &Client,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<JsonLines<T>> {
+        let response = self.send(client.get(self.endpoint(&[path])?).query(query))?;
+
+        Ok(JsonLines {
+            reader: BufReader::new(check(response, &format!("{} stream", path))?),
+            line: String::new(),
+            base_url: self.base_url.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl ClashApi for ClashClient {
+    fn stream_traffic(&self) -> Result<Stream<Traffic>> {
+        Ok(Box::new(self.stream::<Traffic>(
+            &self.stream_client,
+            "traffic",
+            &[],
+        )?))
+    }
+
+    fn stream_logs(&self, level: &str) -> Result<Stream<LogEntry>> {
+        require(level, "Log level")?;
+        Ok(Box::new(self.stream::<LogEntry>(
+            &self.log_client,
+            "logs",
+            &[("level", level)],
+        )?))
+    }
+
+    fn version(&self) -> Result<Version> {
+        self.get_json(&["version"], "version")
+    }
+
+    fn get_proxies(&self) -> Result<ProxiesResponse> {
+        self.get_json(&["proxies"], "proxies")
+    }
+
+    fn get_proxy(&self, name: &str) -> Result<ProxyInfo> {
+        require(name, "Proxy name")?;
+        self.get_json(&["proxies", name], &format!("proxy '{}'", name))
+    }
+
+    fn switch_proxy(&self, group: &str, proxy: &str) -> Result<()> {
+        require(group, "Proxy group name")?;
+        require(proxy, "Proxy name")?;
+
+        let response = self.send(self.client.put(self.endpoint(&["proxies", group])?).json(
+            &SwitchRequest {
+                name: proxy.to_string(),
+            },
+        ))?;
+
+        // The controller answers 400 when the proxy is not in the group or
+        // the group is not a Selector
+        if response.status() == StatusCode::BAD_REQUEST {
+            return Err(ClashError::InvalidSelection(format!(
+                "Cannot select '{}' in '{}': {}",
+                proxy,
+                group,
+                error_message(response)
+            )));
         }
+        check(response, &format!("group '{}'", group))?;
+        Ok(())
+    }
+
+    fn get_proxy_delay(
+        &self,
+        name: &str,
+        url: &str,
+        timeout: u32,
+        expected: Option<&str>,
+    ) -> Result<Option<u32>> {
+        require(name, "Proxy name")?;
+        require(url, "Test URL")?;
+
+        let mut query = vec![("url", url.to_string()), ("timeout", timeout.to_string())];
+        if let Some(expected) = expected {
+            query.push(("expected", expected.to_string()));
+        }
+
+        let response = self.send(
+            self.client
+                .get(self.endpoint(&["proxies", name, "delay"])?)
+                .query(&query)
+                .timeout(self.delay_timeout(timeout)),
+        )?;
+
+        match response.status() {
+            StatusCode::REQUEST_TIMEOUT
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => Ok(None),
+            _ => self
+                .json::<DelayResponse>(response, &format!("proxy '{}'", name))
+                .map(|r| Some(r.delay)),
+        }
+    }
+
+    fn get_group_delay(
+        &self,
+        group: &str,
+        url: &str,
+        timeout: u32,
+        expected: Option<&str>,
+    ) -> Result<HashMap<String, u32>> {
+        require(group, "Proxy group name")?;
+        require(url, "Test URL")?;
+
+        let mut query = vec![("url", url.to_string()), ("timeout", timeout.to_string())];
+        if let Some(expected) = expected {
+            query.push(("expected", expected.to_string()));
+        }
+
+        let response = self.send(
+            self.client
+                .get(self.endpoint(&["group", group, "delay"])?)
+                .query(&query)
+                .timeout(self.delay_timeout(timeout)),
+        )?;
+
+        match response.status() {
+            StatusCode::REQUEST_TIMEOUT
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => Ok(HashMap::new()),
+            _ => self.json(response, &format!("group '{}'", group)),
+        }
+    }
+
+    fn get_connections(&self) -> Result<ConnectionsResponse> {
+        self.get_json(&["connections"], "connections")
+    }
+
+    fn close_all_connections(&self) -> Result<()> {
+        let response = self.send(self.client.delete(self.endpoint(&["connections"])?))?;
+        check(response, "connections")?;
+        Ok(())
+    }
+
+    fn close_connection(&self, id: &str) -> Result<()> {
+        require(id, "Connection id")?;
+
+        let response = self.send(self.client.delete(self.endpoint(&["connections", id])?))?;
+        check(response, &format!("connection '{}'", id))?;
+        Ok(())
+    }
+
+    fn get_rules(&self) -> Result<RulesResponse> {
+        self.get_json(&["rules"], "rules")
+    }
+
+    fn get_proxy_providers(&self) -> Result<ProxyProvidersResponse> {
+        self.get_json(&["providers", "proxies"], "proxy providers")
+    }
+
+    fn get_proxy_provider(&self, name: &str) -> Result<ProxyProvider> {
+        require(name, "Provider name")?;
+        self.get_json(
+            &["providers", "proxies", name],
+            &format!("proxy provider '{}'", name),
+        )
+    }
+
+    fn update_proxy_provider(&self, name: &str) -> Result<()> {
+        require(name, "Provider name")?;
+
+        let response =
+            self.send(
+                self.client
+                    .put(self.endpoint(&["providers", "proxies", name])?),
+            )?;
+        check(response, &format!("proxy provider '{}'", name))?;
+        Ok(())
+    }
+
+    fn healthcheck_proxy_provider(&self, name: &str) -> Result<()> {
+        require(name, "Provider name")?;
+
+        let response = self.send(self.client.get(self.endpoint(&[
+            "providers",
+            "proxies",
+            name,
+            "healthcheck",
+        ])?))?;
+        check(response, &format!("proxy provider '{}'", name))?;
+        Ok(())
+    }
+
+    fn get_rule_providers(&self) -> Result<RuleProvidersResponse> {
+        self.get_json(&["providers", "rules"], "rule providers")
+    }
+
+    fn update_rule_provider(&self, name: &str) -> Result<()> {
+        require(name, "Provider name")?;
+
+        let response =
+            self.send(
+                self.client
+                    .put(self.endpoint(&["providers", "rules", name])?),
+            )?;
+        check(response, &format!("rule provider '{}'", name))?;
+        Ok(())
+    }
+
+    fn get_config(&self) -> Result<Config> {
+        self.get_json(&["configs"], "config")
+    }
+
+    fn set_mode(&self, mode: &str) -> Result<()> {
+        require(mode, "Mode")?;
+
+        let response = self.send(self.client.patch(self.endpoint(&["configs"])?).json(
+            &ModeRequest {
+                mode: mode.to_string(),
+            },
+        ))?;
+        check(response, "config")?;
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread::{self, JoinHandle};
+
+    /// A request received by `mock_server`
+    struct Received {
+        method: String,
+        path: String,
+        body: String,
+    }
+
+    /// Read one request from `stream` and answer it with `status` and JSON
+    /// `body`. Status 0 closes the connection without a response.
+    fn answer(mut stream: impl Read + Write, status: u16, body: &str) -> Received {
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        let header_end = loop {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&request[..header_end]).to_string();
+        let content_length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        while request.len() < header_end + content_length {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+
+        let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+        let received = Received {
+            method: request_line.next().unwrap_or_default().to_string(),
+            path: request_line.next().unwrap_or_default().to_string(),
+            body: String::from_utf8_lossy(&request[header_end..]).to_string(),
+        };
+        if status != 0 {
+            write!(
+                stream,
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+        received
+    }
+
+    /// Answer one request per entry of `responses` (status, JSON body) on a
+    /// local port. Returns the base URL and a handle yielding the requests.
+    fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, JoinHandle<Vec<Received>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|(status, body)| answer(listener.accept().unwrap().0, status, body))
+                .collect()
+        });
+
+        (base_url, handle)
+    }
 
     #[test]
     fn test_client_creation_without_secret() {
-        let client = ClashClient::new("http://localhost:9090".to_string(), None);
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
         assert_eq!(client.base_url, "http://localhost:9090");
     }
 
@@ -133,61 +598,610 @@ mod tests {
         let client = ClashClient::new(
             "http://localhost:9090".to_string(),
             Some("test-secret".to_string()),
-        );
+            ClientOptions::default(),
+        )
+        .unwrap();
         assert_eq!(client.base_url, "http://localhost:9090");
     }
 
     #[test]
     fn test_client_creation_with_empty_secret() {
-        let client = ClashClient::new("http://localhost:9090".to_string(), Some("".to_string()));
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            Some("".to_string()),
+            ClientOptions::default(),
+        )
+        .unwrap();
         assert_eq!(client.base_url, "http://localhost:9090");
     }
 
     #[test]
     fn test_client_with_custom_url() {
-        let client = ClashClient::new("http://example.com:9090".to_string(), None);
+        let client = ClashClient::new(
+            "http://example.com:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
         assert_eq!(client.base_url, "http://example.com:9090");
     }
 
     #[test]
     fn test_version_with_empty_url() {
-        let client = ClashClient::new("".to_string(), None);
+        let client = ClashClient::new("".to_string(), None, ClientOptions::default()).unwrap();
         let result = client.version();
         assert!(result.is_err());
     }
 
     #[test]
     fn test_get_proxies_with_empty_url() {
-        let client = ClashClient::new("".to_string(), None);
+        let client = ClashClient::new("".to_string(), None, ClientOptions::default()).unwrap();
         let result = client.get_proxies();
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_get_rules_with_empty_url() {
+        let client = ClashClient::new("".to_string(), None, ClientOptions::default()).unwrap();
+        let result = client.get_rules();
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_get_proxy_with_empty_name() {
-        let client = ClashClient::new("http://localhost:9090".to_string(), None);
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
         let result = client.get_proxy("");
         assert!(result.is_err());
     }
 
     #[test]
     fn test_switch_proxy_with_empty_group() {
-        let client = ClashClient::new("http://localhost:9090".to_string(), None);
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
         let result = client.switch_proxy("", "proxy");
         assert!(result.is_err());
     }
 
     #[test]
     fn test_switch_proxy_with_empty_proxy() {
-        let client = ClashClient::new("http://localhost:9090".to_string(), None);
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
         let result = client.switch_proxy("group", "");
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_get_proxy_delay_with_empty_name() {
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
+        let result = client.get_proxy_delay("", "http://example.com", 5000, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_group_delay_with_empty_url() {
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
+        let result = client.get_group_delay("GLOBAL", "", 5000, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_close_connection_with_empty_id() {
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
+        let result = client.close_connection("");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stream_traffic_with_empty_url() {
+        let client = ClashClient::new("".to_string(), None, ClientOptions::default()).unwrap();
+        assert!(client.stream_traffic().is_err());
+    }
+
+    #[test]
+    fn test_stream_logs_with_empty_level() {
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
+        assert!(client.stream_logs("").is_err());
+    }
+
+    #[test]
+    fn test_update_proxy_provider_with_empty_name() {
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
+        let result = client.update_proxy_provider("");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_healthcheck_proxy_provider_with_empty_name() {
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
+        let result = client.healthcheck_proxy_provider("");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_update_rule_provider_with_empty_name() {
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
+        let result = client.update_rule_provider("");
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_set_mode_with_empty_mode() {
-        let client = ClashClient::new("http://localhost:9090".to_string(), None);
+        let client = ClashClient::new(
+            "http://localhost:9090".to_string(),
+            None,
+            ClientOptions::default(),
+        )
+        .unwrap();
         let result = client.set_mode("");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_get_proxy_encodes_name() {
+        let (url, server) = mock_server(vec![
+            (200, r#"{"type":"Shadowsocks"}"#),
+            (200, r#"{"type":"Vmess"}"#),
+        ]);
+        let client = ClashClient::new(url, None, ClientOptions::default()).unwrap();
+
+        client.get_proxy("US/03 #x?y").unwrap();
+        client.get_proxy("🇯🇵 Japan 03 | IPLC").unwrap();
+
+        let received = server.join().unwrap();
+        assert_eq!(received[0].method, "GET");
+        assert_eq!(received[0].path, "/proxies/US%2F03%20%23x%3Fy");
+        assert_eq!(
+            received[1].path,
+            "/proxies/%F0%9F%87%AF%F0%9F%87%B5%20Japan%2003%20|%20IPLC"
+        );
+    }
+
+    #[test]
+    fn test_switch_proxy_encodes_group() {
+        let (url, server) = mock_server(vec![(204, "")]);
+        let client = ClashClient::new(url, None, ClientOptions::default()).unwrap();
+
+        client.switch_proxy("Group/1 #a", "HK 01 #2").unwrap();
+
+        let received = server.join().unwrap();
+        assert_eq!(received[0].method, "PUT");
+        assert_eq!(received[0].path, "/proxies/Group%2F1%20%23a");
+        assert_eq!(received[0].body, r#"{"name":"HK 01 #2"}"#);
+    }
+
+    #[test]
+    fn test_delay_and_provider_paths_encode_names() {
+        let (url, server) = mock_server(vec![
+            (200, r#"{"delay":42}"#),
+            (200, r#"{"HK 01":80}"#),
+            (204, ""),
+        ]);
+        // A base URL with a path prefix keeps the prefix
+        let client =
+            ClashClient::new(format!("{}/api/", url), None, ClientOptions::default()).unwrap();
+
+        let delay = client
+            .get_proxy_delay("a/b?c", "http://example.com/?x=1", 100, None)
+            .unwrap();
+        assert_eq!(delay, Some(42));
+        client
+            .get_group_delay("Auto #1", "http://example.com", 100, None)
+            .unwrap();
+        client.update_proxy_provider("sub/one").unwrap();
+
+        let received = server.join().unwrap();
+        assert_eq!(
+            received[0].path,
+            "/api/proxies/a%2Fb%3Fc/delay?url=http%3A%2F%2Fexample.com%2F%3Fx%3D1&timeout=100"
+        );
+        assert_eq!(
+            received[1].path,
+            "/api/group/Auto%20%231/delay?url=http%3A%2F%2Fexample.com&timeout=100"
+        );
+        assert_eq!(received[2].method, "PUT");
+        assert_eq!(received[2].path, "/api/providers/proxies/sub%2Fone");
+    }
+
+    #[test]
+    fn test_status_codes_map_to_errors() {
+        let (url, server) = mock_server(vec![
+            (401, r#"{"message":"Unauthorized"}"#),
+            (404, r#"{"message":"Resource not found"}"#),
+            (
+                400,
+                r#"{"message":"Selector update error: proxy not exist"}"#,
+            ),
+            (500, r#"{"message":"boom"}"#),
+            (200, "not json"),
+        ]);
+        let client =
+            ClashClient::new(url, Some("wrong".to_string()), ClientOptions::default()).unwrap();
+
+        let error = client.get_proxies().unwrap_err();
+        assert!(matches!(
+            error,
+            ClashError::Unauthorized(StatusCode::UNAUTHORIZED)
+        ));
+        assert!(error.to_string().contains("check your secret"));
+        assert!(matches!(
+            client.get_proxy("Nope").unwrap_err(),
+            ClashError::NotFound(subject) if subject == "proxy 'Nope'"
+        ));
+        assert!(matches!(
+            client.switch_proxy("Proxy", "Nope").unwrap_err(),
+            ClashError::InvalidSelection(message) if message.contains("proxy not exist")
+        ));
+        assert!(matches!(
+            client.get_rules().unwrap_err(),
+            ClashError::Http { status: StatusCode::INTERNAL_SERVER_ERROR, body } if body == "boom"
+        ));
+        assert!(matches!(
+            client.get_config().unwrap_err(),
+            ClashError::Decode { .. }
+        ));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_unreachable_and_invalid_request() {
+        // Bind and drop a listener to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client = ClashClient::new(
+            format!("http://127.0.0.1:{}", port),
+            None,
+            ClientOptions {
+                retry_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            client.version().unwrap_err(),
+            ClashError::Unreachable { .. }
+        ));
+        assert!(matches!(
+            client.get_proxy("").unwrap_err(),
+            ClashError::InvalidRequest(_)
+        ));
+    }
+
+    fn fast_retries() -> ClientOptions {
+        ClientOptions {
+            retry_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_retried_after_dropped_connection() {
+        let (url, server) = mock_server(vec![(0, ""), (0, ""), (200, r#"{"mode":"rule"}"#)]);
+        let client = ClashClient::new(url, None, fast_retries()).unwrap();
+
+        assert_eq!(client.get_config().unwrap().mode, "rule");
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_put_not_retried() {
+        let (url, server) = mock_server(vec![(0, "")]);
+        let client = ClashClient::new(url, None, fast_retries()).unwrap();
+
+        assert!(matches!(
+            client.switch_proxy("Proxy", "HK").unwrap_err(),
+            ClashError::Unreachable { .. }
+        ));
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        // Accept connections but never answer
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let _held: Vec<_> = listener.incoming().take(2).collect();
+            thread::sleep(Duration::from_secs(5));
+        });
+
+        let client = ClashClient::new(
+            url,
+            None,
+            ClientOptions {
+                read_timeout: Duration::from_millis(100),
+                retries: 1,
+                ..fast_retries()
+            },
+        )
+        .unwrap();
+        let started = std::time::Instant::now();
+        assert!(matches!(
+            client.get_proxies().unwrap_err(),
+            ClashError::Unreachable { .. }
+        ));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let options = ClientOptions::default();
+        assert_eq!(options.backoff(0), Duration::from_millis(200));
+        assert_eq!(options.backoff(2), Duration::from_millis(800));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket_transport() {
+        use std::os::unix::net::UnixListener;
+
+        let dir = std::env::temp_dir().join(format!("clash-switcher-sock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("clash.sock");
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let responses = [
+            (
+                200,
+                r#"{"proxies":{"GLOBAL":{"type":"Selector","all":["HK 01"],"now":"HK 01"}}}"#,
+            ),
+            (204, ""),
+            (200, "{\"up\":1,\"down\":2}\n"),
+        ];
+        let server = thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|(status, body)| answer(listener.accept().unwrap().0, status, body))
+                .collect::<Vec<_>>()
+        });
+
+        let client = ClashClient::new(
+            format!("unix://{}", path.display()),
+            Some("abc".to_string()),
+            fast_retries(),
+        )
+        .unwrap();
+        let proxies = client.get_proxies().unwrap();
+        assert_eq!(proxies.proxies["GLOBAL"].now, "HK 01");
+        client.switch_proxy("GLOBAL", "HK 01 #2").unwrap();
+        let traffic = client.stream_traffic().unwrap().next().unwrap().unwrap();
+        assert_eq!((traffic.up, traffic.down), (1, 2));
+
+        let received = server.join().unwrap();
+        assert_eq!(received[0].path, "/proxies");
+        assert_eq!(received[1].method, "PUT");
+        assert_eq!(received[1].path, "/proxies/GLOBAL");
+        assert_eq!(received[2].path, "/traffic");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Nothing listens on the socket any more
+        assert!(matches!(
+            client.get_config().unwrap_err(),
+            ClashError::Unreachable { .. }
+        ));
+        assert!(matches!(
+            ClashClient::new("unix://".to_string(), None, ClientOptions::default())
+                .unwrap()
+                .version()
+                .unwrap_err(),
+            ClashError::InvalidRequest(_)
+        ));
+    }
+
+    /// A self-signed HTTPS server answering `responses`. Connections whose
+    /// handshake the client aborts are skipped. Returns the base URL, the
+    /// certificate as PEM, its fingerprint and the number of accepted
+    /// connections.
+    fn tls_server(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, String, String, Arc<AtomicUsize>) {
+        let cert = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()]).unwrap();
+        let cert_pem = cert.cert.pem();
+        let fingerprint = tls::fingerprint(cert.cert.der());
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = Arc::new(
+            rustls::ServerConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(
+                    vec![cert.cert.der().clone()],
+                    rustls::pki_types::PrivateKeyDer::Pkcs8(cert.key_pair.serialize_der().into()),
+                )
+                .unwrap(),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("https://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        thread::spawn(move || {
+            let mut responses = responses.into_iter();
+            let mut next = responses.next();
+            while let Some((status, body)) = next {
+                let (tcp, _) = listener.accept().unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let conn = rustls::ServerConnection::new(config.clone()).unwrap();
+                let mut stream = rustls::StreamOwned::new(conn, tcp);
+                if stream.conn.complete_io(&mut stream.sock).is_err() {
+                    continue;
+                }
+                answer(&mut stream, status, body);
+                stream.conn.send_close_notify();
+                let _ = stream.flush();
+                next = responses.next();
+            }
+        });
+
+        (base_url, cert_pem, fingerprint, connections)
+    }
+
+    fn tls_client(url: &str, tls: TlsOptions) -> Result<ClashClient> {
+        ClashClient::new(
+            url.to_string(),
+            None,
+            ClientOptions {
+                retries: 0,
+                tls,
+                ..Default::default()
+            },
+        )
+    }
+
+    const VERSION: (u16, &str) = (200, r#"{"meta":true,"version":"1.19"}"#);
+
+    #[test]
+    fn test_tls_rejects_unknown_certificate() {
+        let (url, _, _, connections) = tls_server(vec![VERSION]);
+
+        // GET requests are retried by default, but not after a rejected certificate
+        let client = ClashClient::new(url.clone(), None, ClientOptions::default()).unwrap();
+        let error = client.version().unwrap_err();
+        assert!(matches!(error, ClashError::Certificate { .. }));
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        assert!(error.to_string().contains("UnknownIssuer"));
+        assert!(error.to_string().contains("--ca-cert"));
+        assert_eq!(error.exit_code(), 9);
+
+        let pinned = tls_client(
+            &url,
+            TlsOptions {
+                pins: vec!["00".repeat(32)],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let error = pinned.version().unwrap_err();
+        assert!(error.to_string().contains("matches no --pin-sha256"));
+
+        // Unblock the server, which still waits for an accepted handshake
+        tls_client(
+            &url,
+            TlsOptions {
+                insecure: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .version()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_tls_ca_cert_pin_and_insecure() {
+        let (url, cert_pem, fingerprint, _) = tls_server(vec![VERSION, VERSION, VERSION]);
+        let dir = std::env::temp_dir().join(format!("clash-switcher-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ca_path = dir.join("ca.pem");
+        std::fs::write(&ca_path, cert_pem).unwrap();
+
+        let with_ca = TlsOptions {
+            ca_cert: Some(ca_path),
+            ..Default::default()
+        };
+        assert_eq!(
+            tls_client(&url, with_ca)
+                .unwrap()
+                .version()
+                .unwrap()
+                .version,
+            "1.19"
+        );
+
+        let pinned = TlsOptions {
+            pins: vec![fingerprint.to_lowercase()],
+            ..Default::default()
+        };
+        tls_client(&url, pinned).unwrap().version().unwrap();
+
+        let insecure = TlsOptions {
+            insecure: true,
+            ..Default::default()
+        };
+        tls_client(&url, insecure).unwrap().version().unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tls_invalid_options() {
+        let missing = TlsOptions {
+            ca_cert: Some(PathBuf::from("/nonexistent/ca.pem")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            tls_client("https://127.0.0.1:1", missing).err().unwrap(),
+            ClashError::InvalidRequest(_)
+        ));
+
+        let bad_pin = TlsOptions {
+            pins: vec!["not-hex".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            tls_client("https://127.0.0.1:1", bad_pin).err().unwrap(),
+            ClashError::InvalidRequest(_)
+        ));
+
+        let ca_and_pin = TlsOptions {
+            ca_cert: Some(PathBuf::from("/nonexistent/ca.pem")),
+            pins: vec!["AB".repeat(32)],
+            ..Default::default()
+        };
+        assert!(matches!(
+            tls_client("https://127.0.0.1:1", ca_and_pin).err().unwrap(),
+            ClashError::InvalidRequest(message) if message.contains("cannot be combined")
+        ));
+    }
 }
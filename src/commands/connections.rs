@@ -0,0 +1,243 @@
+use crate::api::ClashApi;
+use crate::models::Connection;
+use crate::utils::{format_bytes, pad_string};
+use anyhow::Result;
+use colored::Colorize;
+
+/// Criteria used to select connections. Empty fields match everything.
+#[derive(Default)]
+pub struct ConnectionFilter {
+    /// Substring of the host or destination IP (case-insensitive)
+    pub host: Option<String>,
+    /// Exact name of a proxy or group in the connection chain
+    pub chain: Option<String>,
+    /// Substring of the rule type or payload (case-insensitive)
+    pub rule: Option<String>,
+}
+
+impl ConnectionFilter {
+    pub fn is_empty(&self) -> bool {
+        self.host.is_none() && self.chain.is_none() && self.rule.is_none()
+    }
+
+    pub fn matches(&self, conn: &Connection) -> bool {
+        if let Some(host) = &self.host {
+            let host = host.to_lowercase();
+            if !conn.metadata.host.to_lowercase().contains(&host)
+                && !conn.metadata.destination_ip.contains(&host)
+            {
+                return false;
+            }
+        }
+
+        if let Some(chain) = &self.chain
+            && !conn.chains.iter().any(|c| c == chain)
+        {
+            return false;
+        }
+
+        if let Some(rule) = &self.rule {
+            let rule = rule.to_lowercase();
+            if !conn.rule.to_lowercase().contains(&rule)
+                && !conn.rule_payload.to_lowercase().contains(&rule)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+pub fn execute(client: &impl ClashApi, filter: &ConnectionFilter, close: bool) -> Result<()> {
+    let response = client.get_connections()?;
+
+    let mut matched: Vec<_> = response
+        .connections
+        .iter()
+        .filter(|conn| filter.matches(conn))
+        .collect();
+    matched.sort_by(|a, b| a.start.cmp(&b.start));
+
+    if close {
+        if matched.is_empty() {
+            println!("{} No matching connections", "ℹ".bright_blue());
+            return Ok(());
+        }
+
+        let count = if filter.is_empty() {
+            client.close_all_connections()?;
+            matched.len()
+        } else {
+            close_connections(client, &matched)
+        };
+
+        println!(
+            "{} Closed {} of {} connection(s)",
+            "✓".bright_green().bold(),
+            count,
+            matched.len()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} {} ({} total, ↑ {} ↓ {})\n",
+        "Connections:".bright_yellow().bold(),
+        matched.len(),
+        response.connections.len(),
+        format_bytes(response.upload_total),
+        format_bytes(response.download_total)
+    );
+
+    let host_width = 36;
+    let process_width = 16;
+    let rule_width = 28;
+    let traffic_width = 22;
+
+    println!(
+        "{} {} {} {} {} CHAIN",
+        pad_string("START", 8),
+        pad_string("HOST", host_width),
+        pad_string("PROCESS", process_width),
+        pad_string("RULE", rule_width),
+        pad_string("UP / DOWN", traffic_width),
+    );
+    println!("{}", "-".repeat(130));
+
+    for conn in &matched {
+        let meta = &conn.metadata;
+        let host = if meta.host.is_empty() {
+            &meta.destination_ip
+        } else {
+            &meta.host
+        };
+        let target = format!("{}:{}", host, meta.destination_port);
+        let rule = if conn.rule_payload.is_empty() {
+            conn.rule.clone()
+        } else {
+            format!("{}({})", conn.rule, conn.rule_payload)
+        };
+        // Clash lists the chain from the final node back to the rule target
+        let chain: Vec<_> = conn.chains.iter().rev().map(String::as_str).collect();
+        // Start is RFC 3339; the time of day is enough to spot old connections
+        let start = conn.start.get(11..19).unwrap_or(&conn.start);
+
+        println!(
+            "{} {} {} {} {} {}",
+            pad_string(start, 8).bright_black(),
+            pad_string(&target, host_width),
+            pad_string(&meta.process, process_width),
+            pad_string(&rule, rule_width),
+            pad_string(
+                &format!(
+                    "{} / {}",
+                    format_bytes(conn.upload),
+                    format_bytes(conn.download)
+                ),
+                traffic_width
+            ),
+            chain.join(" → ").bright_cyan()
+        );
+    }
+
+    if !matched.is_empty() {
+        println!("\n{}", "Usage:".bright_yellow());
+        println!(
+            "  {} {}    Close the connections listed above",
+            "connections".green(),
+            "[filters] --close".bright_cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Close each connection individually, returning how many were closed
+pub fn close_connections(client: &impl ClashApi, connections: &[&Connection]) -> usize {
+    connections
+        .iter()
+        .filter(|conn| match client.close_connection(&conn.id) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!(
+                    "{} Failed to close {}: {:#}",
+                    "Warning:".yellow().bold(),
+                    conn.id,
+                    e
+                );
+                false
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
+
+    /// IDs of the fake controller's connections matching `filter`
+    fn matching(fake: &FakeClash, filter: &ConnectionFilter) -> Vec<String> {
+        fake.get_connections()
+            .unwrap()
+            .connections
+            .iter()
+            .filter(|conn| filter.matches(conn))
+            .map(|conn| conn.id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter() {
+        let fake = FakeClash::new();
+        let filter =
+            |host: Option<&str>, chain: Option<&str>, rule: Option<&str>| ConnectionFilter {
+                host: host.map(str::to_string),
+                chain: chain.map(str::to_string),
+                rule: rule.map(str::to_string),
+            };
+
+        assert_eq!(matching(&fake, &filter(None, None, None)), vec!["c1", "c2"]);
+        assert_eq!(
+            matching(&fake, &filter(Some("GOOGLE"), None, None)),
+            vec!["c1"]
+        );
+        // Chains match whole names only
+        assert_eq!(
+            matching(&fake, &filter(None, Some("Proxy"), None)),
+            vec!["c1"]
+        );
+        assert!(matching(&fake, &filter(None, Some("Prox"), None)).is_empty());
+        // Rules match the type or the payload
+        assert_eq!(
+            matching(&fake, &filter(None, None, Some("geoip"))),
+            vec!["c2"]
+        );
+        assert_eq!(
+            matching(&fake, &filter(None, None, Some("google.com"))),
+            vec!["c1"]
+        );
+        assert!(matching(&fake, &filter(Some("google"), Some("DIRECT"), None)).is_empty());
+    }
+
+    #[test]
+    fn test_execute_close_matching() {
+        let fake = FakeClash::new();
+        let filter = ConnectionFilter {
+            chain: Some("DIRECT".to_string()),
+            ..Default::default()
+        };
+
+        execute(&fake, &filter, true).unwrap();
+        assert_eq!(fake.state.lock().unwrap().closed_connections, vec!["c2"]);
+        assert_eq!(matching(&fake, &ConnectionFilter::default()), vec!["c1"]);
+    }
+
+    #[test]
+    fn test_execute_close_all() {
+        let fake = FakeClash::new();
+        execute(&fake, &ConnectionFilter::default(), true).unwrap();
+        assert!(matching(&fake, &ConnectionFilter::default()).is_empty());
+    }
+}
@@ -1,84 +1,288 @@
-use crate::client::ClashClient;
+use crate::api::ClashApi;
+use crate::chain::Chain;
+use crate::commands::rules::rule_counts;
+use crate::error::ClashError;
+use crate::models::{Config, ProxyInfo};
+use crate::output::{self, OutputFormat, Render};
+use crate::utils::{NameMatch, match_name};
 use anyhow::Result;
 use colored::Colorize;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 
-pub fn execute(client: &ClashClient) -> Result<()> {
+#[derive(Serialize)]
+pub struct CurrentStatus {
+    pub mode: String,
+    pub description: String,
+    /// From GLOBAL to the active node, empty if GLOBAL is missing
+    pub chain: Chain,
+}
+
+/// The chain of one group and how many rules route to it
+#[derive(Serialize)]
+pub struct GroupChain {
+    pub group: String,
+    /// Number of rules whose target is this group
+    pub rules: usize,
+    pub chain: Chain,
+}
+
+#[derive(Serialize)]
+pub struct GroupChains {
+    pub mode: String,
+    pub groups: Vec<GroupChain>,
+}
+
+impl Render for CurrentStatus {
+    fn render_table(&self) {
+        // Display current Clash mode
+        println!("\n{}", "Current Status:".bright_yellow().bold());
+        println!();
+        println!(
+            "{} {}",
+            "Clash Mode:".bright_yellow(),
+            self.mode.bright_green().bold()
+        );
+        println!(
+            "{} {}",
+            "           ".bright_yellow(),
+            self.description.bright_black()
+        );
+        println!();
+
+        if self.chain.hops.is_empty() {
+            println!("{}", "GLOBAL selector not found".red());
+            return;
+        }
+
+        println!("{}", "GLOBAL Proxy Chain:".bright_yellow().bold());
+        println!();
+        self.chain.print_tree(true);
+
+        // Show the type of final node
+        if let Some(node_type) = self.chain.node().and_then(|hop| hop.proxy_type.as_ref()) {
+            println!();
+            println!("  {} {}", "Type:".bright_yellow(), node_type);
+        }
+    }
+
+    fn render_plain(&self) {
+        println!("{}\t{}", self.mode, self.chain.names().join("\t"));
+    }
+}
+
+pub fn status(client: &impl ClashApi) -> Result<CurrentStatus> {
     let config = client.get_config()?;
     let proxies = client.get_proxies()?;
+    Ok(build_status(config, &proxies.proxies))
+}
 
-    // Display current Clash mode
-    println!("\n{}", "Current Status:".bright_yellow().bold());
-    println!();
-    println!(
-        "{} {}",
-        "Clash Mode:".bright_yellow(),
-        config.mode.bright_green().bold()
-    );
-
-    let mode_desc = match config.mode.to_lowercase().as_str() {
+pub fn build_status(config: Config, proxies: &HashMap<String, ProxyInfo>) -> CurrentStatus {
+    let description = match config.mode.to_lowercase().as_str() {
         "global" => "All traffic goes through GLOBAL proxy",
         "rule" => "Traffic routing based on rules",
         "direct" => "All traffic bypasses proxy",
         _ => "Unknown mode",
     };
-    println!(
-        "{} {}",
-        "           ".bright_yellow(),
-        mode_desc.bright_black()
-    );
-    println!();
 
     // Start from GLOBAL selector
-    if let Some(global) = proxies.proxies.get("GLOBAL") {
-        println!("{}", "GLOBAL Proxy Chain:".bright_yellow().bold());
-        println!();
+    let chain = if proxies.contains_key("GLOBAL") {
+        Chain::resolve(proxies, "GLOBAL")
+    } else {
+        Chain::default()
+    };
 
-        let mut chain = vec!["GLOBAL".to_string()];
-        let mut current_proxy = global;
-
-        // Follow the chain until we reach a non-selector node
-        while !current_proxy.now.is_empty() {
-            let next_name = &current_proxy.now;
-            chain.push(next_name.clone());
-
-            // Try to get the next proxy in chain
-            if let Some(next_proxy) = proxies.proxies.get(next_name) {
-                // Check if it's a selector or the final node
-                if next_proxy.proxy_type == "Selector" || next_proxy.proxy_type == "URLTest" {
-                    current_proxy = next_proxy;
-                } else {
-                    // Reached final node
-                    break;
-                }
-            } else {
-                break;
-            }
+    CurrentStatus {
+        mode: config.mode,
+        description: description.to_string(),
+        chain,
+    }
+}
+
+pub fn execute(client: &impl ClashApi, format: OutputFormat) -> Result<()> {
+    let status = status(client)?;
+    output::print(&status, format)
+}
+
+impl Render for GroupChains {
+    fn render_table(&self) {
+        println!(
+            "\n{} {}",
+            "Clash Mode:".bright_yellow(),
+            self.mode.bright_green().bold()
+        );
+
+        for group in &self.groups {
+            let usage = match group.rules {
+                0 => "not used by rules".bright_black(),
+                1 => "1 rule".bright_green(),
+                n => format!("{} rules", n).bright_green(),
+            };
+            println!();
+            println!("{} ({})", group.group.bright_yellow().bold(), usage);
+            group.chain.print_tree(true);
+        }
+    }
+
+    fn render_plain(&self) {
+        for group in &self.groups {
+            println!(
+                "{}\t{}\t{}",
+                group.group,
+                group.rules,
+                group.chain.names().join("\t")
+            );
         }
+    }
+}
+
+/// Groups that are not a member of any other group besides GLOBAL, or that
+/// rules route to directly, in the order GLOBAL lists them, with GLOBAL
+/// itself last
+pub fn top_level_groups(
+    proxies: &HashMap<String, ProxyInfo>,
+    counts: &HashMap<&str, usize>,
+) -> Vec<String> {
+    let nested: HashSet<&str> = proxies
+        .iter()
+        .filter(|(name, info)| info.proxy_type.is_group() && *name != "GLOBAL")
+        .flat_map(|(_, info)| info.all.iter().map(String::as_str))
+        .collect();
+    let order: &[String] = proxies
+        .get("GLOBAL")
+        .map(|global| global.all.as_slice())
+        .unwrap_or_default();
+
+    let mut groups: Vec<String> = proxies
+        .iter()
+        .filter(|(name, info)| {
+            info.proxy_type.is_group()
+                && *name != "GLOBAL"
+                && (!nested.contains(name.as_str()) || counts.contains_key(name.as_str()))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    groups.sort_by_key(|name| {
+        (
+            order.iter().position(|n| n == name).unwrap_or(usize::MAX),
+            name.clone(),
+        )
+    });
+
+    if proxies.contains_key("GLOBAL") {
+        groups.push("GLOBAL".to_string());
+    }
+    groups
+}
+
+/// Show the chain of `group`, or of every top-level group when `None`
+pub fn execute_groups(
+    client: &impl ClashApi,
+    group: Option<&str>,
+    format: OutputFormat,
+) -> Result<()> {
+    let config = client.get_config()?;
+    let proxies = client.get_proxies()?.proxies;
+    let rules = client.get_rules()?.rules;
+    let counts = rule_counts(&rules);
 
-        // Display the chain
-        for (i, name) in chain.iter().enumerate() {
-            if i == 0 {
-                println!("  {} {}", "┌─".bright_blue(), name.bright_cyan());
-            } else if i == chain.len() - 1 {
-                println!(
-                    "  {} {} {}",
-                    "└─→".bright_green(),
-                    name.bright_green().bold(),
-                    "(active)".bright_black()
-                );
-            } else {
-                println!("  {} {}", "├─→".bright_blue(), name.bright_cyan());
+    let names = match group {
+        Some(query) => {
+            let mut groups: Vec<String> = proxies
+                .iter()
+                .filter(|(_, info)| info.proxy_type.is_group())
+                .map(|(name, _)| name.clone())
+                .collect();
+            groups.sort();
+            match match_name(query, &groups) {
+                NameMatch::Unique(name) => vec![name.to_string()],
+                NameMatch::Ambiguous(matches) => {
+                    return Err(ClashError::InvalidSelection(format!(
+                        "'{}' matches several groups: {}",
+                        query,
+                        matches.join(", ")
+                    ))
+                    .into());
+                }
+                NameMatch::NotFound => {
+                    return Err(
+                        ClashError::NotFound(format!("proxy group matching '{}'", query)).into(),
+                    );
+                }
             }
         }
+        None => top_level_groups(&proxies, &counts),
+    };
 
-        // Show the type of final node
-        if let Some(final_proxy) = proxies.proxies.get(&chain[chain.len() - 1]) {
-            println!();
-            println!("  {} {}", "Type:".bright_yellow(), final_proxy.proxy_type);
+    let groups = names
+        .into_iter()
+        .map(|name| GroupChain {
+            rules: counts.get(name.as_str()).copied().unwrap_or(0),
+            chain: Chain::resolve(&proxies, &name),
+            group: name,
+        })
+        .collect();
+
+    output::print(
+        &GroupChains {
+            mode: config.mode,
+            groups,
+        },
+        format,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
+    use crate::models::ProxyType;
+
+    fn group(proxy_type: ProxyType, all: &[&str]) -> ProxyInfo {
+        ProxyInfo {
+            proxy_type,
+            all: all.iter().map(|s| s.to_string()).collect(),
+            now: all.first().map(|s| s.to_string()).unwrap_or_default(),
+            history: Vec::new(),
         }
-    } else {
-        println!("{}", "GLOBAL selector not found".red());
     }
 
-    Ok(())
+    #[test]
+    fn test_top_level_groups() {
+        let proxies: HashMap<String, ProxyInfo> = [
+            (
+                "GLOBAL",
+                group(ProxyType::Selector, &["Streaming", "Proxy", "Auto"]),
+            ),
+            ("Proxy", group(ProxyType::Selector, &["Auto", "HK 01"])),
+            ("Auto", group(ProxyType::URLTest, &["HK 01"])),
+            ("Streaming", group(ProxyType::Fallback, &["HK 01"])),
+            ("Zeta", group(ProxyType::LoadBalance, &["HK 01"])),
+            ("HK 01", group(ProxyType::Unknown("Vmess".to_string()), &[])),
+        ]
+        .into_iter()
+        .map(|(name, info)| (name.to_string(), info))
+        .collect();
+
+        assert_eq!(
+            top_level_groups(&proxies, &HashMap::new()),
+            vec!["Streaming", "Proxy", "Zeta", "GLOBAL"]
+        );
+
+        // Rules can route to a group nested in another one
+        let counts = HashMap::from([("Auto", 3)]);
+        assert_eq!(
+            top_level_groups(&proxies, &counts),
+            vec!["Streaming", "Proxy", "Auto", "Zeta", "GLOBAL"]
+        );
+    }
+
+    #[test]
+    fn test_status() {
+        let fake = FakeClash::new();
+        let status = status(&fake).unwrap();
+        assert_eq!(status.mode, "Rule");
+        assert_eq!(status.chain.names(), vec!["GLOBAL", "Proxy", "🇭🇰 HK 01"]);
+        assert_eq!(status.chain.node().unwrap().delay, Some(120));
+    }
 }
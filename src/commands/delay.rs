@@ -0,0 +1,187 @@
+use crate::api::ClashApi;
+use crate::error::ClashError;
+use crate::models::ProxyInfo;
+use crate::utils::pad_string;
+use anyhow::Result;
+use colored::Colorize;
+use unicode_width::UnicodeWidthStr;
+
+pub fn execute(
+    client: &impl ClashApi,
+    name: &str,
+    url: &str,
+    timeout: u32,
+    expected: Option<&str>,
+) -> Result<()> {
+    let proxies = client.get_proxies()?;
+
+    // Exact name first, then a unique case-insensitive prefix
+    let target = if proxies.proxies.contains_key(name) {
+        name.to_string()
+    } else {
+        let matches: Vec<_> = proxies
+            .proxies
+            .keys()
+            .filter(|n| n.to_lowercase().starts_with(&name.to_lowercase()))
+            .collect();
+
+        match matches.len() {
+            0 => return Err(ClashError::NotFound(format!("proxy matching '{}'", name)).into()),
+            1 => matches[0].clone(),
+            _ => {
+                eprintln!("Multiple matches found:");
+                for n in matches {
+                    eprintln!("  - {}", n);
+                }
+                return Err(
+                    ClashError::InvalidSelection("Please be more specific.".to_string()).into(),
+                );
+            }
+        }
+    };
+
+    let info = &proxies.proxies[&target];
+    if info.proxy_type.is_group() {
+        println!(
+            "{} {} ({} members)...",
+            "Testing".bright_yellow(),
+            target.bright_cyan(),
+            info.all.len()
+        );
+    }
+    let results = measure(client, &target, info, url, timeout, expected)?;
+
+    let name_width = results
+        .iter()
+        .map(|(name, _)| name.width())
+        .max()
+        .unwrap_or(0)
+        .clamp(10, 40);
+
+    println!();
+    println!("{} DELAY", pad_string("NAME", name_width));
+    println!("{}", "-".repeat(name_width + 10));
+
+    for (name, delay) in &results {
+        let delay_str = match delay {
+            Some(ms) if *ms < 200 => format!("{}ms", ms).bright_green(),
+            Some(ms) if *ms < 500 => format!("{}ms", ms).bright_yellow(),
+            Some(ms) => format!("{}ms", ms).red(),
+            None => failure_label(expected).red().bold(),
+        };
+        let marker = if !info.now.is_empty() && name == &info.now {
+            "●".bright_green()
+        } else {
+            " ".normal()
+        };
+        println!("{} {} {}", pad_string(name, name_width), delay_str, marker);
+    }
+
+    let healthy = results.iter().filter(|(_, d)| d.is_some()).count();
+    println!();
+    println!(
+        "{} {}/{} reachable (url: {}, timeout: {}ms)",
+        "Summary:".bright_yellow(),
+        healthy,
+        results.len(),
+        url,
+        timeout
+    );
+
+    Ok(())
+}
+
+/// Delay-test `target`: every member of a group, or the node itself. Sorted
+/// fastest first, failed tests last.
+fn measure(
+    client: &impl ClashApi,
+    target: &str,
+    info: &ProxyInfo,
+    url: &str,
+    timeout: u32,
+    expected: Option<&str>,
+) -> Result<Vec<(String, Option<u32>)>> {
+    // Groups are tested as a whole, everything else as a single node
+    let mut results: Vec<(String, Option<u32>)> = if !info.proxy_type.is_group() {
+        vec![(
+            target.to_string(),
+            client.get_proxy_delay(target, url, timeout, expected)?,
+        )]
+    } else {
+        let delays = client.get_group_delay(target, url, timeout, expected)?;
+        info.all
+            .iter()
+            .map(|member| (member.clone(), delays.get(member).copied()))
+            .collect()
+    };
+
+    results.sort_by_key(|(name, delay)| (delay.is_none(), *delay, name.clone()));
+    Ok(results)
+}
+
+/// Shown for a failed test. With `--expected` the controller also fails
+/// tests that got another status code, so it is not always a timeout.
+fn failure_label(expected: Option<&str>) -> &'static str {
+    if expected.is_some() {
+        "failed"
+    } else {
+        "timeout"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
+
+    fn run(fake: &FakeClash, name: &str) -> Vec<(String, Option<u32>)> {
+        let proxies = fake.get_proxies().unwrap().proxies;
+        measure(fake, name, &proxies[name], "http://example.com", 100, None).unwrap()
+    }
+
+    #[test]
+    fn test_measure_group_sorted() {
+        let fake = FakeClash::new();
+        assert_eq!(
+            run(&fake, "Proxy"),
+            vec![
+                ("🇯🇵 Japan 03 | IPLC x1.5".to_string(), Some(80)),
+                ("🇭🇰 HK 01".to_string(), Some(120)),
+                ("🇯🇵 Japan 04".to_string(), Some(300)),
+                ("Auto".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_measure_single_node() {
+        let fake = FakeClash::new();
+        assert_eq!(
+            run(&fake, "🇯🇵 Japan 04"),
+            vec![("🇯🇵 Japan 04".to_string(), Some(300))]
+        );
+        assert_eq!(run(&fake, "DIRECT"), vec![("DIRECT".to_string(), None)]);
+    }
+
+    #[test]
+    fn test_failure_label() {
+        assert_eq!(failure_label(None), "timeout");
+        assert_eq!(failure_label(Some("204")), "failed");
+    }
+
+    #[test]
+    fn test_execute() {
+        let fake = FakeClash::new();
+        execute(&fake, "prox", "http://example.com", 100, None).unwrap();
+        execute(&fake, "🇯🇵 japan 04", "http://example.com", 100, Some("204")).unwrap();
+
+        let error = |name: &str| {
+            execute(&fake, name, "http://example.com", 100, None)
+                .unwrap_err()
+                .downcast::<ClashError>()
+                .unwrap()
+        };
+        assert!(matches!(error("🇯🇵"), ClashError::InvalidSelection(_)));
+        assert!(matches!(error("korea"), ClashError::NotFound(_)));
+    }
+}
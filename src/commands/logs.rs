@@ -0,0 +1,169 @@
+use crate::api::ClashApi;
+use crate::error::ClashError;
+use crate::models::LogEntry;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use regex::Regex;
+use std::thread;
+use std::time::Duration;
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Substring and regex filters applied to log payloads
+pub struct LogFilter {
+    grep: Option<String>,
+    regex: Option<Regex>,
+}
+
+impl LogFilter {
+    pub fn new(grep: Option<String>, regex: Option<&str>) -> Result<Self> {
+        let regex = regex
+            .map(Regex::new)
+            .transpose()
+            .context("Invalid regular expression")?;
+
+        Ok(Self {
+            grep: grep.map(|g| g.to_lowercase()),
+            regex,
+        })
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if let Some(grep) = &self.grep
+            && !entry.payload.to_lowercase().contains(grep)
+        {
+            return false;
+        }
+
+        if let Some(regex) = &self.regex
+            && !regex.is_match(&entry.payload)
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+pub fn execute(
+    client: &impl ClashApi,
+    level: &str,
+    filter: &LogFilter,
+    json: bool,
+    reconnect: bool,
+) -> Result<()> {
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        match client.stream_logs(level) {
+            Ok(stream) => {
+                for entry in stream {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        // One undecodable message does not end the stream
+                        Err(e @ ClashError::Decode { .. }) => {
+                            eprintln!(
+                                "{} {:#}",
+                                "Warning:".yellow().bold(),
+                                anyhow::Error::from(e)
+                            );
+                            continue;
+                        }
+                        Err(e) if !reconnect => return Err(e.into()),
+                        Err(e) => {
+                            eprintln!("{} {:#}", "Warning:".yellow().bold(), e);
+                            break;
+                        }
+                    };
+
+                    // The controller is alive, so start over with a short delay
+                    backoff = Duration::from_secs(1);
+
+                    if filter.matches(&entry) {
+                        print_entry(&entry, json)?;
+                    }
+                }
+
+                if !reconnect {
+                    return Ok(());
+                }
+                eprintln!(
+                    "{} Log stream ended, reconnecting in {}s...",
+                    "ℹ".bright_blue(),
+                    backoff.as_secs()
+                );
+            }
+            Err(e) => {
+                if !reconnect || !is_transient(&e) {
+                    return Err(e.into());
+                }
+                eprintln!(
+                    "{} {:#}, retrying in {}s...",
+                    "Warning:".yellow().bold(),
+                    e,
+                    backoff.as_secs()
+                );
+            }
+        }
+
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Whether reconnecting may help: the controller is down or failing, as
+/// opposed to rejecting the request
+fn is_transient(error: &ClashError) -> bool {
+    match error {
+        ClashError::Unreachable { .. } => true,
+        ClashError::Http { status, .. } => status.is_server_error(),
+        _ => false,
+    }
+}
+
+fn print_entry(entry: &LogEntry, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(entry)?);
+        return Ok(());
+    }
+
+    let level = format!("{:<7}", entry.level.to_uppercase());
+    let level = match entry.level.as_str() {
+        "debug" => level.bright_black(),
+        "info" => level.bright_blue(),
+        "warning" => level.yellow(),
+        "error" => level.red().bold(),
+        _ => level.normal(),
+    };
+    println!("{} {}", level, entry.payload);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_is_transient() {
+        let unreachable = ClashError::Unreachable {
+            url: "http://localhost:9090".to_string(),
+            source: "connection refused".into(),
+        };
+        assert!(is_transient(&unreachable));
+
+        let http = |status| ClashError::Http {
+            status,
+            body: String::new(),
+        };
+        assert!(is_transient(&http(StatusCode::BAD_GATEWAY)));
+        assert!(!is_transient(&http(StatusCode::BAD_REQUEST)));
+        assert!(!is_transient(&ClashError::Unauthorized(
+            StatusCode::UNAUTHORIZED
+        )));
+        assert!(!is_transient(&ClashError::InvalidRequest(
+            "Log level is empty".to_string()
+        )));
+    }
+}
@@ -1,6 +1,17 @@
+pub mod connections;
 pub mod current;
+pub mod delay;
+pub mod logs;
 pub mod mode;
+pub mod profile;
+pub mod providers;
 pub mod proxies;
 pub mod proxy;
+pub mod route;
+pub mod rule_providers;
+pub mod rules;
+pub mod status;
 pub mod switch;
+pub mod traffic;
+pub mod tui;
 pub mod version;
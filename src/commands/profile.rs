@@ -0,0 +1,123 @@
+use crate::settings::{DEFAULT_URL, Profile, Settings};
+use crate::utils::pad_string;
+use anyhow::Result;
+use colored::Colorize;
+use std::path::Path;
+
+pub fn add(path: &Path, name: &str, profile: Profile, make_default: bool) -> Result<()> {
+    anyhow::ensure!(!name.is_empty(), "Profile name is empty");
+
+    let mut settings = Settings::load(path)?;
+    let replaced = settings
+        .profiles
+        .insert(name.to_string(), profile)
+        .is_some();
+    if make_default || settings.default.is_none() {
+        settings.default = Some(name.to_string());
+    }
+    settings.save(path)?;
+
+    println!(
+        "{} {} profile '{}' in {}",
+        "✓".bright_green().bold(),
+        if replaced { "Updated" } else { "Added" },
+        name.bright_cyan(),
+        path.display()
+    );
+
+    Ok(())
+}
+
+pub fn list(path: &Path) -> Result<()> {
+    let settings = Settings::load(path)?;
+
+    if settings.profiles.is_empty() {
+        println!(
+            "{} No profiles in {}. Add one with 'profile add <name> --url <url>'.",
+            "ℹ".bright_blue(),
+            path.display()
+        );
+        return Ok(());
+    }
+
+    println!("Profiles:\n");
+    let name_width = 18;
+    let url_width = 32;
+
+    println!(
+        "  {} {} {} GROUP",
+        pad_string("NAME", name_width),
+        pad_string("URL", url_width),
+        pad_string("SECRET", 8)
+    );
+    println!("{}", "-".repeat(75));
+
+    for (name, profile) in &settings.profiles {
+        let marker = if settings.default.as_deref() == Some(name) {
+            "●".bright_green()
+        } else {
+            " ".normal()
+        };
+        println!(
+            "{} {} {} {} {}",
+            marker,
+            pad_string(name, name_width).bright_cyan(),
+            pad_string(profile.url.as_deref().unwrap_or(DEFAULT_URL), url_width),
+            pad_string(secret_source(profile), 8),
+            profile.group.as_deref().unwrap_or("-")
+        );
+    }
+
+    Ok(())
+}
+
+fn secret_source(profile: &Profile) -> &'static str {
+    if profile.secret.is_some() {
+        "yes"
+    } else if profile.secret_file.is_some() {
+        "file"
+    } else {
+        "no"
+    }
+}
+
+pub fn remove(path: &Path, name: &str) -> Result<()> {
+    let mut settings = Settings::load(path)?;
+
+    if settings.profiles.remove(name).is_none() {
+        anyhow::bail!("Profile '{}' not found", name);
+    }
+    if settings.default.as_deref() == Some(name) {
+        settings.default = None;
+    }
+    settings.save(path)?;
+
+    println!(
+        "{} Removed profile '{}'",
+        "✓".bright_green().bold(),
+        name.bright_cyan()
+    );
+
+    Ok(())
+}
+
+pub fn set_default(path: &Path, name: &str) -> Result<()> {
+    let mut settings = Settings::load(path)?;
+
+    if !settings.profiles.contains_key(name) {
+        anyhow::bail!(
+            "Profile '{}' not found. Use 'profile list' to see available profiles.",
+            name
+        );
+    }
+    settings.default = Some(name.to_string());
+    settings.save(path)?;
+
+    println!(
+        "{} Default profile is now '{}'",
+        "✓".bright_green().bold(),
+        name.bright_cyan()
+    );
+
+    Ok(())
+}
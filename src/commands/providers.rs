@@ -0,0 +1,195 @@
+use crate::api::ClashApi;
+use crate::error::ClashError;
+use crate::models::ProxyProvider;
+use crate::utils::{format_timestamp, pad_string};
+use anyhow::Result;
+use colored::Colorize;
+
+/// Providers the user can act on, sorted by name. The built-in `Compatible`
+/// provider only wraps proxies from the config file and cannot be updated.
+fn user_providers(client: &impl ClashApi) -> Result<Vec<ProxyProvider>> {
+    let mut providers: Vec<_> = client
+        .get_proxy_providers()?
+        .providers
+        .into_values()
+        .filter(|p| p.vehicle_type != "Compatible")
+        .collect();
+    providers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(providers)
+}
+
+/// Last update time for display. Go's zero time means the provider was
+/// never fetched.
+pub fn format_updated_at(updated_at: &str) -> String {
+    if updated_at.is_empty() || updated_at.starts_with("0001-") {
+        "never".to_string()
+    } else {
+        format_timestamp(updated_at)
+    }
+}
+
+pub fn list(client: &impl ClashApi) -> Result<()> {
+    let providers = user_providers(client)?;
+
+    if providers.is_empty() {
+        println!("{} No proxy providers configured", "ℹ".bright_blue());
+        return Ok(());
+    }
+
+    println!("Proxy Providers:\n");
+    let name_width = 24;
+    let vehicle_width = 10;
+    let count_width = 8;
+
+    println!(
+        "{} {} {} UPDATED",
+        pad_string("NAME", name_width),
+        pad_string("VEHICLE", vehicle_width),
+        pad_string("PROXIES", count_width)
+    );
+    println!("{}", "-".repeat(70));
+
+    for provider in &providers {
+        println!(
+            "{} {} {} {}",
+            pad_string(&provider.name, name_width).bright_cyan(),
+            pad_string(&provider.vehicle_type, vehicle_width),
+            pad_string(&provider.proxies.len().to_string(), count_width),
+            format_updated_at(&provider.updated_at)
+        );
+    }
+
+    Ok(())
+}
+
+pub fn show(client: &impl ClashApi, name: &str) -> Result<()> {
+    let provider = client.get_proxy_provider(name)?;
+
+    println!(
+        "\n{} {}",
+        "Provider:".bright_yellow(),
+        provider.name.bright_cyan()
+    );
+    println!("{} {}", "Type:".bright_yellow(), provider.provider_type);
+    println!("{} {}", "Vehicle:".bright_yellow(), provider.vehicle_type);
+    println!(
+        "{} {}",
+        "Updated:".bright_yellow(),
+        format_updated_at(&provider.updated_at)
+    );
+
+    println!(
+        "\n{} ({}):",
+        "Proxies".bright_yellow(),
+        provider.proxies.len()
+    );
+    for (i, proxy) in provider.proxies.iter().enumerate() {
+        let delay = match proxy.history.last() {
+            Some(item) if item.delay > 0 => format!("{}ms", item.delay).bright_green(),
+            Some(_) => "timeout".red(),
+            None => "-".bright_black(),
+        };
+        println!(
+            "  {}. {} {} {}",
+            i + 1,
+            pad_string(&proxy.name, 36),
+            pad_string(proxy.proxy_type.as_str(), 12),
+            delay
+        );
+    }
+
+    Ok(())
+}
+
+/// Update one provider, or every provider when `name` is `None`
+pub fn update(client: &impl ClashApi, name: Option<&str>) -> Result<()> {
+    let names = provider_names(client, name)?;
+    run_for_each(&names, "Updated", |n| client.update_proxy_provider(n))
+}
+
+/// Health check one provider, or every provider when `name` is `None`
+pub fn healthcheck(client: &impl ClashApi, name: Option<&str>) -> Result<()> {
+    let names = provider_names(client, name)?;
+    run_for_each(&names, "Checked", |n| client.healthcheck_proxy_provider(n))
+}
+
+fn provider_names(client: &impl ClashApi, name: Option<&str>) -> Result<Vec<String>> {
+    match name {
+        Some(name) => Ok(vec![name.to_string()]),
+        None => Ok(updatable(
+            user_providers(client)?
+                .into_iter()
+                .map(|p| (p.name, p.vehicle_type)),
+        )),
+    }
+}
+
+/// Names of the providers that `--all` acts on. Only providers fetched over
+/// HTTP or from a file can be updated; the controller rejects `Inline` and
+/// `Compatible` ones, so those are reported as skipped instead.
+pub fn updatable(providers: impl IntoIterator<Item = (String, String)>) -> Vec<String> {
+    let mut names = Vec::new();
+    for (name, vehicle_type) in providers {
+        if matches!(vehicle_type.as_str(), "HTTP" | "File") {
+            names.push(name);
+        } else {
+            println!(
+                "{} Skipped '{}' ({} provider)",
+                "-".bright_black(),
+                name.bright_cyan(),
+                vehicle_type
+            );
+        }
+    }
+    names
+}
+
+/// Run `action` for every provider, reporting each result and failing if
+/// any of them failed
+pub fn run_for_each(
+    names: &[String],
+    done: &str,
+    action: impl Fn(&str) -> Result<(), ClashError>,
+) -> Result<()> {
+    let mut failed = 0;
+    for name in names {
+        match action(name) {
+            Ok(()) => println!(
+                "{} {} '{}'",
+                "✓".bright_green().bold(),
+                done,
+                name.bright_cyan()
+            ),
+            Err(e) => {
+                failed += 1;
+                println!("{} {}: {:#}", "✗".red().bold(), name.bright_cyan(), e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} provider(s) failed", failed, names.len());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
+
+    #[test]
+    fn test_all_skips_providers_without_vehicle() {
+        let fake = FakeClash::new();
+        update(&fake, None).unwrap();
+        healthcheck(&fake, None).unwrap();
+        assert_eq!(
+            fake.state.lock().unwrap().provider_actions,
+            vec!["update subscription", "healthcheck subscription"]
+        );
+
+        // Named explicitly, the controller's error is reported
+        assert!(update(&fake, Some("local")).is_err());
+    }
+}
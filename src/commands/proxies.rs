@@ -1,62 +1,122 @@
-use crate::client::ClashClient;
+use crate::api::ClashApi;
+use crate::models::ProxyType;
+use crate::output::{self, OutputFormat, Render};
 use crate::utils::pad_string;
 use anyhow::Result;
 use colored::Colorize;
+use serde::Serialize;
 
-pub fn execute(client: &ClashClient) -> Result<()> {
+#[derive(Serialize)]
+pub struct GroupSummary {
+    pub id: usize,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub group_type: ProxyType,
+    pub now: String,
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct ProxyGroups(pub Vec<GroupSummary>);
+
+impl Render for ProxyGroups {
+    fn render_table(&self) {
+        println!("Proxy Groups:\n");
+        let id_width = 5;
+        let name_width = 30;
+        let type_width = 12;
+
+        println!(
+            "{} {} {} CURRENT",
+            pad_string("ID", id_width),
+            pad_string("NAME", name_width),
+            pad_string("TYPE", type_width)
+        );
+        println!("{}", "-".repeat(85));
+
+        for group in &self.0 {
+            let current = if !group.now.is_empty() {
+                &group.now
+            } else {
+                "N/A"
+            };
+
+            let id_str = format!("[{}]", group.id);
+            println!(
+                "{} {} {} {}",
+                pad_string(&id_str.bright_cyan().to_string(), id_width + 9), // +9 for ANSI color codes
+                pad_string(&group.name, name_width),
+                pad_string(group.group_type.as_str(), type_width),
+                current
+            );
+        }
+
+        println!("\n{}", "Usage:".bright_yellow());
+        println!(
+            "  {} {} {}    View details by ID",
+            "proxy".green(),
+            "1".bright_cyan(),
+            " ".repeat(7)
+        );
+        println!(
+            "  {} {}  View details by name prefix",
+            "proxy".green(),
+            "GLOBAL".bright_cyan()
+        );
+    }
+
+    fn render_plain(&self) {
+        for group in &self.0 {
+            println!(
+                "{}\t{}\t{}\t{}",
+                group.id, group.name, group.group_type, group.now
+            );
+        }
+    }
+}
+
+pub fn list_groups(client: &impl ClashApi) -> Result<ProxyGroups> {
     let proxies = client.get_proxies()?;
 
-    // Filter and display only Selector and URLTest types (proxy groups)
+    // Filter and display only proxy groups
     let mut groups: Vec<_> = proxies
         .proxies
-        .iter()
-        .filter(|(_, info)| info.proxy_type == "Selector" || info.proxy_type == "URLTest")
+        .into_iter()
+        .filter(|(_, info)| info.proxy_type.is_group())
         .collect();
 
-    groups.sort_by_key(|(name, _)| *name);
-
-    println!("Proxy Groups:\n");
-    let id_width = 5;
-    let name_width = 30;
-    let type_width = 12;
-
-    println!(
-        "{} {} {} CURRENT",
-        pad_string("ID", id_width),
-        pad_string("NAME", name_width),
-        pad_string("TYPE", type_width)
-    );
-    println!("{}", "-".repeat(85));
-
-    for (idx, (name, info)) in groups.iter().enumerate() {
-        let current = if !info.now.is_empty() {
-            &info.now
-        } else {
-            "N/A"
-        };
-
-        let id_str = format!("[{}]", idx + 1);
-        println!(
-            "{} {} {} {}",
-            pad_string(&id_str.bright_cyan().to_string(), id_width + 9), // +9 for ANSI color codes
-            pad_string(name, name_width),
-            pad_string(&info.proxy_type, type_width),
-            current
-        );
-    }
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-    println!("\n{}", "Usage:".bright_yellow());
-    println!(
-        "  {} {} {}    View details by ID",
-        "proxy".green(),
-        "1".bright_cyan(),
-        " ".repeat(7)
-    );
-    println!(
-        "  {} {}  View details by name prefix",
-        "proxy".green(),
-        "GLOBAL".bright_cyan()
-    );
-
-    Ok(())
+    Ok(ProxyGroups(
+        groups
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (name, info))| GroupSummary {
+                id: idx + 1,
+                name,
+                group_type: info.proxy_type,
+                now: info.now,
+            })
+            .collect(),
+    ))
+}
+
+pub fn execute(client: &impl ClashApi, format: OutputFormat) -> Result<()> {
+    let groups = list_groups(client)?;
+    output::print(&groups, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
+
+    #[test]
+    fn test_list_groups() {
+        let groups = list_groups(&FakeClash::new()).unwrap();
+        let names: Vec<&str> = groups.0.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["Auto", "GLOBAL", "Proxy", "Streaming"]);
+        assert_eq!(groups.0[3].id, 4);
+        assert_eq!(groups.0[3].group_type, ProxyType::Fallback);
+    }
 }
@@ -1,8 +1,63 @@
-use crate::client::ClashClient;
+use crate::api::ClashApi;
+use crate::error::ClashError;
+use crate::models::{HistoryItem, ProxyType};
+use crate::output::{self, OutputFormat, Render};
 use anyhow::Result;
 use colored::Colorize;
+use serde::Serialize;
 
-pub fn execute(client: &ClashClient, name: &str) -> Result<()> {
+#[derive(Serialize)]
+pub struct ProxyDetails {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub proxy_type: ProxyType,
+    pub now: String,
+    pub all: Vec<String>,
+    pub history: Vec<HistoryItem>,
+}
+
+impl Render for ProxyDetails {
+    fn render_table(&self) {
+        println!("\n{} {}", "Proxy:".bright_yellow(), self.name.bright_cyan());
+        println!("{} {}", "Type:".bright_yellow(), self.proxy_type);
+
+        if !self.now.is_empty() {
+            println!("{} {}", "Current:".bright_yellow(), self.now.bright_green());
+        }
+
+        if !self.all.is_empty() {
+            println!(
+                "\n{} ({}):",
+                "Available proxies".bright_yellow(),
+                self.all.len()
+            );
+            for (i, node) in self.all.iter().enumerate() {
+                let marker = if !self.now.is_empty() && node == &self.now {
+                    "●".bright_green()
+                } else {
+                    "○".normal()
+                };
+                println!("  {} {}. {}", marker, i + 1, node);
+            }
+        }
+
+        if !self.history.is_empty() {
+            println!("\n{}:", "Latency history".bright_yellow());
+            for item in &self.history {
+                println!("  {} - {}ms", item.time, item.delay);
+            }
+        }
+    }
+
+    fn render_plain(&self) {
+        println!("{}\t{}\t{}", self.name, self.proxy_type, self.now);
+        for node in &self.all {
+            println!("{}", node);
+        }
+    }
+}
+
+pub fn execute(client: &impl ClashApi, name: &str, format: OutputFormat) -> Result<()> {
     // Try to parse as number first
     let proxy_name = if let Ok(idx) = name.parse::<usize>() {
         // Get proxy by index
@@ -10,12 +65,15 @@ pub fn execute(client: &ClashClient, name: &str) -> Result<()> {
         let mut groups: Vec<_> = proxies
             .proxies
             .iter()
-            .filter(|(_, info)| info.proxy_type == "Selector" || info.proxy_type == "URLTest")
+            .filter(|(_, info)| info.proxy_type.is_group())
             .collect();
         groups.sort_by_key(|(name, _)| *name);
 
         if idx == 0 || idx > groups.len() {
-            anyhow::bail!("Invalid proxy group number. Use 'proxies' to see available groups.");
+            return Err(ClashError::InvalidSelection(
+                "Invalid proxy group number. Use 'proxies' to see available groups.".to_string(),
+            )
+            .into());
         }
         groups[idx - 1].0.to_string()
     } else {
@@ -30,59 +88,51 @@ pub fn execute(client: &ClashClient, name: &str) -> Result<()> {
             .collect();
 
         if matches.is_empty() {
-            anyhow::bail!(
-                "No proxy group found matching '{}'. Use 'proxies' to see available groups.",
-                name
-            );
+            return Err(ClashError::NotFound(format!("proxy group matching '{}'", name)).into());
         } else if matches.len() > 1 {
-            println!("Multiple matches found:");
+            eprintln!("Multiple matches found:");
             for (n, _) in matches {
-                println!("  - {}", n);
+                eprintln!("  - {}", n);
             }
-            anyhow::bail!("Please be more specific or use the ID number.");
+            return Err(ClashError::InvalidSelection(
+                "Please be more specific or use the ID number.".to_string(),
+            )
+            .into());
         } else {
             matches[0].0.to_string()
         }
     };
 
     let proxy = client.get_proxy(&proxy_name)?;
-    println!(
-        "\n{} {}",
-        "Proxy:".bright_yellow(),
-        proxy_name.bright_cyan()
-    );
-    println!("{} {}", "Type:".bright_yellow(), proxy.proxy_type);
+    let details = ProxyDetails {
+        name: proxy_name,
+        proxy_type: proxy.proxy_type,
+        now: proxy.now,
+        all: proxy.all,
+        history: proxy.history,
+    };
 
-    if !proxy.now.is_empty() {
-        println!(
-            "{} {}",
-            "Current:".bright_yellow(),
-            proxy.now.bright_green()
-        );
-    }
+    output::print(&details, format)
+}
 
-    if !proxy.all.is_empty() {
-        println!(
-            "\n{} ({}):",
-            "Available proxies".bright_yellow(),
-            proxy.all.len()
-        );
-        for (i, node) in proxy.all.iter().enumerate() {
-            let marker = if !proxy.now.is_empty() && node == &proxy.now {
-                "●".bright_green()
-            } else {
-                "○".normal()
-            };
-            println!("  {} {}. {}", marker, i + 1, node);
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
 
-    if !proxy.history.is_empty() {
-        println!("\n{}:", "Latency history".bright_yellow());
-        for item in &proxy.history {
-            println!("  {} - {}ms", item.time, item.delay);
-        }
-    }
+    #[test]
+    fn test_execute_selection_errors() {
+        let fake = FakeClash::new();
+        let error = |name: &str| {
+            execute(&fake, name, OutputFormat::Json)
+                .unwrap_err()
+                .downcast::<ClashError>()
+                .unwrap()
+        };
 
-    Ok(())
+        execute(&fake, "1", OutputFormat::Json).unwrap();
+        assert!(matches!(error("0"), ClashError::InvalidSelection(_)));
+        assert!(matches!(error("99"), ClashError::InvalidSelection(_)));
+        assert!(matches!(error("korea"), ClashError::NotFound(_)));
+    }
 }
@@ -0,0 +1,382 @@
+use crate::api::ClashApi;
+use crate::chain::Chain;
+use crate::commands::rules::normalize_rule_type;
+use crate::models::Rule;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use reqwest::Url;
+use std::net::IpAddr;
+
+/// The destination being routed
+#[derive(Debug, PartialEq)]
+pub struct Target {
+    /// Lowercased domain, or empty when the destination is an IP address
+    pub domain: String,
+    pub ip: Option<IpAddr>,
+    pub port: Option<u16>,
+}
+
+impl Target {
+    /// Parse a URL (`https://example.com/path`) or a bare `host[:port]`
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        anyhow::ensure!(!input.is_empty(), "Host is empty");
+
+        if input.contains("://") {
+            let url = Url::parse(input).with_context(|| format!("Invalid URL '{}'", input))?;
+            let port = url.port_or_known_default();
+            let host = url
+                .host_str()
+                .with_context(|| format!("URL '{}' has no host", input))?;
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            return Ok(Self::from_host(host, port));
+        }
+
+        // [v6]:port or [v6]
+        if let Some(rest) = input.strip_prefix('[') {
+            let (ip, port) = rest
+                .split_once(']')
+                .with_context(|| format!("Invalid host '{}'", input))?;
+            let ip: IpAddr = ip
+                .parse()
+                .with_context(|| format!("Invalid IPv6 address '{}'", ip))?;
+            let port = match port.strip_prefix(':') {
+                Some(p) => Some(p.parse().with_context(|| format!("Invalid port '{}'", p))?),
+                None => None,
+            };
+            return Ok(Self::from_ip(ip, port));
+        }
+
+        // A bare IPv6 address contains colons but no port
+        if let Ok(ip) = input.parse::<IpAddr>() {
+            return Ok(Self::from_ip(ip, None));
+        }
+
+        match input.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .with_context(|| format!("Invalid port '{}'", port))?;
+                Ok(Self::from_host(host, Some(port)))
+            }
+            None => Ok(Self::from_host(input, None)),
+        }
+    }
+
+    fn from_host(host: &str, port: Option<u16>) -> Self {
+        match host.parse::<IpAddr>() {
+            Ok(ip) => Self::from_ip(ip, port),
+            Err(_) => Self {
+                domain: host.trim_end_matches('.').to_lowercase(),
+                ip: None,
+                port,
+            },
+        }
+    }
+
+    fn from_ip(ip: IpAddr, port: Option<u16>) -> Self {
+        Self {
+            domain: String::new(),
+            ip: Some(ip),
+            port,
+        }
+    }
+}
+
+/// Outcome of evaluating a single rule against a target
+#[derive(Debug, PartialEq)]
+pub enum RuleMatch {
+    Match,
+    NoMatch,
+    /// The rule depends on information not available locally (DNS
+    /// resolution, GeoIP data, the requesting process, ...)
+    Undecidable,
+}
+
+/// Evaluate `rule` against `target` the way Clash would, as far as that is
+/// possible without the controller's runtime state
+pub fn evaluate(rule: &Rule, target: &Target) -> RuleMatch {
+    let rule_type = normalize_rule_type(&rule.rule_type);
+    let payload = rule.payload.to_lowercase();
+
+    let matched = match rule_type.as_str() {
+        "MATCH" | "FINAL" => true,
+        "DOMAIN" => !target.domain.is_empty() && target.domain == payload,
+        "DOMAINSUFFIX" => {
+            !target.domain.is_empty()
+                && (target.domain == payload || target.domain.ends_with(&format!(".{}", payload)))
+        }
+        "DOMAINKEYWORD" => !target.domain.is_empty() && target.domain.contains(&payload),
+        "IPCIDR" | "IPCIDR6" => match target.ip {
+            Some(ip) => match cidr_contains(&payload, ip) {
+                Some(contained) => contained,
+                None => return RuleMatch::Undecidable,
+            },
+            // Clash would resolve the domain first
+            None => return RuleMatch::Undecidable,
+        },
+        "DSTPORT" => match target.port {
+            Some(port) => match port_matches(&payload, port) {
+                Some(matched) => matched,
+                None => return RuleMatch::Undecidable,
+            },
+            None => return RuleMatch::Undecidable,
+        },
+        _ => return RuleMatch::Undecidable,
+    };
+
+    if matched {
+        RuleMatch::Match
+    } else {
+        RuleMatch::NoMatch
+    }
+}
+
+/// Whether `ip` is inside `cidr`, or `None` if the CIDR cannot be parsed
+fn cidr_contains(cidr: &str, ip: IpAddr) -> Option<bool> {
+    let (network, prefix) = cidr.split_once('/')?;
+    let network: IpAddr = network.parse().ok()?;
+    let prefix: u32 = prefix.parse().ok()?;
+
+    match (network, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) if prefix <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            Some(u32::from(net) & mask == u32::from(ip) & mask)
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) if prefix <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            Some(u128::from(net) & mask == u128::from(ip) & mask)
+        }
+        (IpAddr::V4(_), IpAddr::V6(_)) | (IpAddr::V6(_), IpAddr::V4(_)) => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether `port` matches a payload such as `443`, `8000-9000` or `80/443`,
+/// or `None` if the payload cannot be parsed
+fn port_matches(payload: &str, port: u16) -> Option<bool> {
+    let mut matched = false;
+    for item in payload.split(['/', ',']) {
+        let item = item.trim();
+        match item.split_once('-') {
+            Some((start, end)) => {
+                let start: u16 = start.trim().parse().ok()?;
+                let end: u16 = end.trim().parse().ok()?;
+                matched |= (start..=end).contains(&port);
+            }
+            None => matched |= item.parse::<u16>().ok()? == port,
+        }
+    }
+    Some(matched)
+}
+
+/// `TYPE,payload` as written in a config, or just `TYPE` for `MATCH`
+fn rule_label(rule: &Rule) -> String {
+    if rule.payload.is_empty() {
+        rule.rule_type.clone()
+    } else {
+        format!("{},{}", rule.rule_type, rule.payload)
+    }
+}
+
+pub fn execute(client: &impl ClashApi, input: &str) -> Result<()> {
+    let target = Target::parse(input)?;
+    let config = client.get_config()?;
+    let proxies = client.get_proxies()?;
+
+    println!(
+        "\n{} {}",
+        "Route for".bright_yellow().bold(),
+        input.bright_cyan()
+    );
+    println!();
+
+    let start = match config.mode.to_lowercase().as_str() {
+        "global" => {
+            println!(
+                "{} Clash is in Global mode, rules are bypassed",
+                "ℹ".bright_blue()
+            );
+            "GLOBAL".to_string()
+        }
+        "direct" => {
+            println!(
+                "{} Clash is in Direct mode, rules are bypassed",
+                "ℹ".bright_blue()
+            );
+            "DIRECT".to_string()
+        }
+        _ => {
+            let rules = client.get_rules()?;
+            let mut undecidable = Vec::new();
+            let mut matched = None;
+
+            for (idx, rule) in rules.rules.iter().enumerate() {
+                match evaluate(rule, &target) {
+                    RuleMatch::Match => {
+                        matched = Some((idx, rule));
+                        break;
+                    }
+                    RuleMatch::NoMatch => {}
+                    RuleMatch::Undecidable => undecidable.push((idx, rule)),
+                }
+            }
+
+            if !undecidable.is_empty() {
+                println!(
+                    "{} {} earlier rule(s) are undecidable locally and may match first:",
+                    "Warning:".yellow().bold(),
+                    undecidable.len()
+                );
+                for (idx, rule) in &undecidable {
+                    println!(
+                        "  {} {} → {}",
+                        format!("[{}]", idx + 1).bright_black(),
+                        rule_label(rule),
+                        rule.proxy
+                    );
+                }
+                println!();
+            }
+
+            let Some((idx, rule)) = matched else {
+                println!("{}", "No rule matched".red());
+                return Ok(());
+            };
+
+            println!(
+                "{} {} {} → {}",
+                "Rule:".bright_yellow(),
+                format!("[{}]", idx + 1).bright_cyan(),
+                rule_label(rule),
+                rule.proxy.bright_green()
+            );
+            println!();
+            rule.proxy.clone()
+        }
+    };
+
+    let chain = Chain::resolve(&proxies.proxies, &start);
+
+    println!("{}", "Proxy Chain:".bright_yellow().bold());
+    chain.print_tree(false);
+
+    if let Some(node_type) = chain.node().and_then(|hop| hop.proxy_type.as_ref()) {
+        println!();
+        println!("  {} {}", "Type:".bright_yellow(), node_type);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(rule_type: &str, payload: &str) -> Rule {
+        Rule {
+            rule_type: rule_type.to_string(),
+            payload: payload.to_string(),
+            proxy: "Proxy".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_target_parse() {
+        let t = Target::parse("https://www.Example.com/path?q=1").unwrap();
+        assert_eq!(t.domain, "www.example.com");
+        assert_eq!(t.port, Some(443));
+
+        let t = Target::parse("example.com:8080").unwrap();
+        assert_eq!(t.domain, "example.com");
+        assert_eq!(t.port, Some(8080));
+
+        let t = Target::parse("10.1.2.3").unwrap();
+        assert_eq!(t.ip, Some("10.1.2.3".parse().unwrap()));
+        assert!(t.domain.is_empty());
+        assert_eq!(t.port, None);
+
+        let t = Target::parse("[::1]:22").unwrap();
+        assert_eq!(t.ip, Some("::1".parse().unwrap()));
+        assert_eq!(t.port, Some(22));
+
+        assert!(Target::parse("example.com:http").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_domain_rules() {
+        let t = Target::parse("www.google.com").unwrap();
+        assert_eq!(
+            evaluate(&rule("DomainSuffix", "google.com"), &t),
+            RuleMatch::Match
+        );
+        assert_eq!(
+            evaluate(&rule("DOMAIN-SUFFIX", "gle.com"), &t),
+            RuleMatch::NoMatch
+        );
+        assert_eq!(
+            evaluate(&rule("Domain", "www.google.com"), &t),
+            RuleMatch::Match
+        );
+        assert_eq!(
+            evaluate(&rule("DomainKeyword", "goog"), &t),
+            RuleMatch::Match
+        );
+        assert_eq!(evaluate(&rule("Match", ""), &t), RuleMatch::Match);
+    }
+
+    #[test]
+    fn test_evaluate_ip_rules() {
+        let t = Target::parse("10.1.2.3").unwrap();
+        assert_eq!(
+            evaluate(&rule("IPCIDR", "10.0.0.0/8"), &t),
+            RuleMatch::Match
+        );
+        assert_eq!(
+            evaluate(&rule("IP-CIDR", "192.168.0.0/16"), &t),
+            RuleMatch::NoMatch
+        );
+        assert_eq!(
+            evaluate(&rule("IPCIDR6", "fd00::/8"), &t),
+            RuleMatch::NoMatch
+        );
+        assert_eq!(
+            evaluate(&rule("DomainSuffix", "10.1.2.3"), &t),
+            RuleMatch::NoMatch
+        );
+
+        // A domain would need DNS resolution first
+        let t = Target::parse("example.com").unwrap();
+        assert_eq!(
+            evaluate(&rule("IPCIDR", "10.0.0.0/8"), &t),
+            RuleMatch::Undecidable
+        );
+    }
+
+    #[test]
+    fn test_evaluate_port_rules() {
+        let t = Target::parse("example.com:8443").unwrap();
+        assert_eq!(evaluate(&rule("DstPort", "8443"), &t), RuleMatch::Match);
+        assert_eq!(
+            evaluate(&rule("DST-PORT", "8000-9000"), &t),
+            RuleMatch::Match
+        );
+        assert_eq!(evaluate(&rule("DstPort", "80/443"), &t), RuleMatch::NoMatch);
+
+        let t = Target::parse("example.com").unwrap();
+        assert_eq!(
+            evaluate(&rule("DstPort", "443"), &t),
+            RuleMatch::Undecidable
+        );
+    }
+
+    #[test]
+    fn test_evaluate_unsupported_rules() {
+        let t = Target::parse("example.com").unwrap();
+        assert_eq!(evaluate(&rule("GeoIP", "CN"), &t), RuleMatch::Undecidable);
+        assert_eq!(
+            evaluate(&rule("ProcessName", "curl"), &t),
+            RuleMatch::Undecidable
+        );
+    }
+}
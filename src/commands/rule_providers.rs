@@ -0,0 +1,86 @@
+use crate::api::ClashApi;
+use crate::commands::providers::{format_updated_at, run_for_each, updatable};
+use crate::models::RuleProvider;
+use crate::utils::pad_string;
+use anyhow::Result;
+use colored::Colorize;
+
+fn sorted_providers(client: &impl ClashApi) -> Result<Vec<RuleProvider>> {
+    let mut providers: Vec<_> = client
+        .get_rule_providers()?
+        .providers
+        .into_values()
+        .collect();
+    providers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(providers)
+}
+
+pub fn list(client: &impl ClashApi) -> Result<()> {
+    let providers = sorted_providers(client)?;
+
+    if providers.is_empty() {
+        println!("{} No rule providers configured", "ℹ".bright_blue());
+        return Ok(());
+    }
+
+    println!("Rule Providers:\n");
+    let name_width = 24;
+    let behavior_width = 10;
+    let format_width = 12;
+    let vehicle_width = 8;
+    let count_width = 8;
+
+    println!(
+        "{} {} {} {} {} UPDATED",
+        pad_string("NAME", name_width),
+        pad_string("BEHAVIOR", behavior_width),
+        pad_string("FORMAT", format_width),
+        pad_string("VEHICLE", vehicle_width),
+        pad_string("RULES", count_width)
+    );
+    println!("{}", "-".repeat(90));
+
+    for provider in &providers {
+        println!(
+            "{} {} {} {} {} {}",
+            pad_string(&provider.name, name_width).bright_cyan(),
+            pad_string(&provider.behavior, behavior_width),
+            pad_string(&provider.format, format_width),
+            pad_string(&provider.vehicle_type, vehicle_width),
+            pad_string(&provider.rule_count.to_string(), count_width),
+            format_updated_at(&provider.updated_at)
+        );
+    }
+
+    Ok(())
+}
+
+/// Update one rule provider, or every provider when `name` is `None`
+pub fn update(client: &impl ClashApi, name: Option<&str>) -> Result<()> {
+    let names = match name {
+        Some(name) => vec![name.to_string()],
+        None => updatable(
+            sorted_providers(client)?
+                .into_iter()
+                .map(|p| (p.name, p.vehicle_type)),
+        ),
+    };
+
+    run_for_each(&names, "Updated", |n| client.update_rule_provider(n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
+
+    #[test]
+    fn test_update_all_skips_inline() {
+        let fake = FakeClash::new();
+        update(&fake, None).unwrap();
+        assert_eq!(
+            fake.state.lock().unwrap().provider_actions,
+            vec!["update reject"]
+        );
+    }
+}
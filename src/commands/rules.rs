@@ -0,0 +1,208 @@
+use crate::api::ClashApi;
+use crate::models::Rule;
+use crate::utils::pad_string;
+use anyhow::Result;
+use colored::Colorize;
+use std::collections::HashMap;
+
+/// Criteria used to select rules. Empty fields match everything.
+#[derive(Default)]
+pub struct RuleFilter {
+    /// Rule type, in the API (`DomainSuffix`) or config (`DOMAIN-SUFFIX`)
+    /// spelling
+    pub rule_type: Option<String>,
+    /// Substring of the payload (case-insensitive)
+    pub payload: Option<String>,
+    /// Target proxy or group, compared case-insensitively
+    pub target: Option<String>,
+}
+
+impl RuleFilter {
+    pub fn matches(&self, rule: &Rule) -> bool {
+        if let Some(rule_type) = &self.rule_type
+            && normalize_rule_type(&rule.rule_type) != normalize_rule_type(rule_type)
+        {
+            return false;
+        }
+
+        if let Some(payload) = &self.payload
+            && !rule
+                .payload
+                .to_lowercase()
+                .contains(&payload.to_lowercase())
+        {
+            return false;
+        }
+
+        if let Some(target) = &self.target
+            && rule.proxy.to_lowercase() != target.to_lowercase()
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Rule type in a form comparable across spellings: the API reports
+/// `DomainSuffix` while configs use `DOMAIN-SUFFIX`, both become `DOMAINSUFFIX`
+pub fn normalize_rule_type(rule_type: &str) -> String {
+    rule_type
+        .chars()
+        .filter(|c| *c != '-' && *c != '_')
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Number of rules routing to each target
+pub fn rule_counts<'a>(rules: impl IntoIterator<Item = &'a Rule>) -> HashMap<&'a str, usize> {
+    let mut counts = HashMap::new();
+    for rule in rules {
+        *counts.entry(rule.proxy.as_str()).or_default() += 1;
+    }
+    counts
+}
+
+pub fn execute(client: &impl ClashApi, filter: &RuleFilter, count: bool) -> Result<()> {
+    let response = client.get_rules()?;
+
+    // Keep the original position; rule order decides which one matches first
+    let matched: Vec<_> = response
+        .rules
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| filter.matches(rule))
+        .collect();
+
+    if count {
+        let mut counts: Vec<_> = rule_counts(matched.iter().map(|(_, rule)| *rule))
+            .into_iter()
+            .collect();
+        counts.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+
+        println!("{}\n", "Rules per target:".bright_yellow().bold());
+        println!("{} COUNT", pad_string("TARGET", 30));
+        println!("{}", "-".repeat(40));
+        for (target, n) in counts {
+            println!("{} {}", pad_string(target, 30), n);
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{} {} of {}\n",
+        "Rules:".bright_yellow().bold(),
+        matched.len(),
+        response.rules.len()
+    );
+
+    let id_width = 7;
+    let type_width = 16;
+    let payload_width = 40;
+
+    println!(
+        "{} {} {} TARGET",
+        pad_string("ID", id_width),
+        pad_string("TYPE", type_width),
+        pad_string("PAYLOAD", payload_width)
+    );
+    println!("{}", "-".repeat(85));
+
+    for (idx, rule) in matched {
+        let id_str = format!("[{}]", idx + 1);
+        println!(
+            "{} {} {} {}",
+            pad_string(&id_str, id_width).bright_cyan(),
+            pad_string(&rule.rule_type, type_width),
+            pad_string(&rule.payload, payload_width),
+            rule.proxy.bright_green()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_counts() {
+        let rule = |proxy: &str| Rule {
+            rule_type: "DomainSuffix".to_string(),
+            payload: "example.com".to_string(),
+            proxy: proxy.to_string(),
+        };
+        let rules = vec![rule("Proxy"), rule("DIRECT"), rule("Proxy")];
+        let counts = rule_counts(&rules);
+        assert_eq!(counts["Proxy"], 2);
+        assert_eq!(counts["DIRECT"], 1);
+        assert!(!counts.contains_key("GLOBAL"));
+    }
+
+    fn rule(rule_type: &str, payload: &str, proxy: &str) -> Rule {
+        Rule {
+            rule_type: rule_type.to_string(),
+            payload: payload.to_string(),
+            proxy: proxy.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_filter_rule_type() {
+        let suffix = rule("DomainSuffix", "google.com", "Proxy");
+        for spelling in [
+            "DomainSuffix",
+            "DOMAIN-SUFFIX",
+            "domain_suffix",
+            "domainsuffix",
+        ] {
+            let filter = RuleFilter {
+                rule_type: Some(spelling.to_string()),
+                ..Default::default()
+            };
+            assert!(filter.matches(&suffix), "{}", spelling);
+        }
+
+        let filter = RuleFilter {
+            rule_type: Some("DOMAIN".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&suffix));
+    }
+
+    #[test]
+    fn test_filter_payload_and_target() {
+        let google = rule("DomainSuffix", "google.com", "Proxy");
+
+        let payload = RuleFilter {
+            payload: Some("GOOGLE".to_string()),
+            ..Default::default()
+        };
+        assert!(payload.matches(&google));
+        assert!(!payload.matches(&rule("DomainSuffix", "netflix.com", "Proxy")));
+
+        // Targets must match exactly, ignoring case
+        let target = RuleFilter {
+            target: Some("proxy".to_string()),
+            ..Default::default()
+        };
+        assert!(target.matches(&google));
+        assert!(!target.matches(&rule("DomainSuffix", "google.com", "Proxy HK")));
+
+        assert!(RuleFilter::default().matches(&google));
+    }
+
+    #[test]
+    fn test_filter_combined() {
+        let filter = RuleFilter {
+            rule_type: Some("DOMAIN-SUFFIX".to_string()),
+            payload: Some("google".to_string()),
+            target: Some("Proxy".to_string()),
+        };
+        assert!(filter.matches(&rule("DomainSuffix", "google.com", "Proxy")));
+        assert!(!filter.matches(&rule("DomainKeyword", "google", "Proxy")));
+        assert!(!filter.matches(&rule("DomainSuffix", "youtube.com", "Proxy")));
+        assert!(!filter.matches(&rule("DomainSuffix", "google.com", "DIRECT")));
+    }
+}
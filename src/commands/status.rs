@@ -0,0 +1,236 @@
+use crate::api::ClashApi;
+use crate::commands::current::build_status;
+use crate::utils::format_rate;
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_TEMPLATE: &str = "{mode} {group}:{node}";
+
+/// Values available to the status template
+#[derive(Default)]
+pub struct StatusFields {
+    pub mode: String,
+    /// Group that selected the final node
+    pub group: String,
+    pub node: String,
+    /// Type of the final node (e.g., Shadowsocks)
+    pub node_type: String,
+    /// Full GLOBAL chain
+    pub chain: Vec<String>,
+    /// Last recorded latency of the final node
+    pub delay: Option<u32>,
+    pub up: Option<u64>,
+    pub down: Option<u64>,
+}
+
+impl StatusFields {
+    fn get(&self, key: &str) -> Option<String> {
+        let value = match key {
+            "mode" => self.mode.clone(),
+            "group" => self.group.clone(),
+            "node" => self.node.clone(),
+            "type" => self.node_type.clone(),
+            "chain" => self.chain.join(" → "),
+            "delay" => self.delay.map_or("-".to_string(), |d| d.to_string()),
+            "up" => self.up.map_or("-".to_string(), format_rate),
+            "down" => self.down.map_or("-".to_string(), format_rate),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Substitute `{name}` placeholders. Unknown placeholders are left as-is.
+pub fn render_template(template: &str, fields: &StatusFields) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let key = &after[..end];
+                match fields.get(key) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        out.push('{');
+                        out.push_str(key);
+                        out.push('}');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
+
+/// Waybar custom module output
+#[derive(Serialize)]
+struct WaybarOutput {
+    text: String,
+    tooltip: String,
+    class: String,
+}
+
+fn needs_traffic(template: &str) -> bool {
+    template.contains("{up}") || template.contains("{down}")
+}
+
+fn collect(client: &impl ClashApi, with_traffic: bool) -> Result<StatusFields> {
+    let config = client.get_config()?;
+    let proxies = client.get_proxies()?;
+    let status = build_status(config, &proxies.proxies);
+
+    let chain = status.chain;
+    let node = chain.node();
+
+    let (up, down) = if with_traffic {
+        match client.stream_traffic()?.next() {
+            Some(traffic) => {
+                let traffic = traffic?;
+                (Some(traffic.up), Some(traffic.down))
+            }
+            None => (None, None),
+        }
+    } else {
+        (None, None)
+    };
+
+    Ok(StatusFields {
+        mode: status.mode,
+        group: chain
+            .group()
+            .map(|hop| hop.name.clone())
+            .unwrap_or_default(),
+        node: node.map(|hop| hop.name.clone()).unwrap_or_default(),
+        node_type: node
+            .and_then(|hop| hop.proxy_type.as_ref())
+            .map(|t| t.to_string())
+            .unwrap_or_default(),
+        delay: node.and_then(|hop| hop.delay),
+        chain: chain.names().into_iter().map(str::to_string).collect(),
+        up,
+        down,
+    })
+}
+
+fn print_once(client: &impl ClashApi, template: &str, waybar: bool) -> Result<()> {
+    let fields = collect(client, needs_traffic(template));
+
+    if waybar {
+        // Waybar expects a line even when the controller is unreachable
+        let output = match &fields {
+            Ok(fields) => WaybarOutput {
+                text: render_template(template, fields),
+                tooltip: format!(
+                    "Mode: {}\n{}\nType: {}",
+                    fields.mode,
+                    fields.chain.join(" → "),
+                    fields.node_type
+                ),
+                class: fields.mode.to_lowercase(),
+            },
+            Err(e) => WaybarOutput {
+                text: "clash: offline".to_string(),
+                tooltip: format!("{:#}", e),
+                class: "error".to_string(),
+            },
+        };
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        println!("{}", render_template(template, &fields?));
+    }
+
+    io::stdout().flush()?;
+    Ok(())
+}
+
+pub fn execute(
+    client: &impl ClashApi,
+    template: &str,
+    waybar: bool,
+    watch: Option<u64>,
+) -> Result<()> {
+    let Some(interval) = watch else {
+        return print_once(client, template, waybar);
+    };
+
+    loop {
+        if let Err(e) = print_once(client, template, waybar) {
+            // Keep the bar alive; the controller may come back
+            println!("clash: {:#}", e);
+        }
+        thread::sleep(Duration::from_secs(interval.max(1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
+
+    fn fields() -> StatusFields {
+        StatusFields {
+            mode: "Rule".to_string(),
+            group: "Proxy".to_string(),
+            node: "HK 01".to_string(),
+            node_type: "Shadowsocks".to_string(),
+            chain: vec!["GLOBAL".into(), "Proxy".into(), "HK 01".into()],
+            delay: Some(88),
+            up: Some(1024),
+            down: None,
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        assert_eq!(
+            render_template("{mode} {group}:{node} {delay}ms", &fields()),
+            "Rule Proxy:HK 01 88ms"
+        );
+        assert_eq!(
+            render_template("↑{up} ↓{down} [{type}]", &fields()),
+            "↑1.0 KB/s ↓- [Shadowsocks]"
+        );
+        assert_eq!(
+            render_template("{chain}", &fields()),
+            "GLOBAL → Proxy → HK 01"
+        );
+    }
+
+    #[test]
+    fn test_render_template_unknown_and_unclosed() {
+        assert_eq!(render_template("{nope} {mode}", &fields()), "{nope} Rule");
+        assert_eq!(render_template("{mode} {node", &fields()), "Rule {node");
+        assert_eq!(render_template("", &fields()), "");
+    }
+
+    #[test]
+    fn test_needs_traffic() {
+        assert!(needs_traffic("{up}"));
+        assert!(!needs_traffic(DEFAULT_TEMPLATE));
+    }
+
+    #[test]
+    fn test_collect() {
+        let fields = collect(&FakeClash::new(), true).unwrap();
+        assert_eq!(
+            render_template(
+                "{mode} {group}:{node} {delay}ms ↑{up} ↓{down} {type}",
+                &fields
+            ),
+            "Rule Proxy:🇭🇰 HK 01 120ms ↑1.0 KB/s ↓2.0 KB/s Shadowsocks"
+        );
+    }
+}
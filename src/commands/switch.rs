@@ -1,17 +1,111 @@
-use crate::client::ClashClient;
-use crate::utils::pad_string;
+use crate::api::ClashApi;
+use crate::chain::Chain;
+use crate::commands::connections::{self, ConnectionFilter};
+use crate::error::ClashError;
+use crate::models::ProxyInfo;
+use crate::output::{self, OutputFormat, Render};
+use crate::utils::{NameMatch, match_name, pad_string};
 use anyhow::{Context, Result};
 use colored::Colorize;
-use std::io::{self, Write};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
-pub fn execute(client: &ClashClient, group: Option<String>, proxy: Option<String>) -> Result<()> {
+/// Settings for `switch --fastest`
+pub struct FastestOptions {
+    pub url: String,
+    pub timeout: u32,
+    pub exclude: Option<String>,
+    pub tolerance: u32,
+    pub workers: usize,
+}
+
+#[derive(Serialize)]
+pub struct SwitchOutcome {
+    pub group: String,
+    pub previous: String,
+    pub proxy: String,
+    /// False when `--fastest` kept the current node
+    pub switched: bool,
+    /// Measured latency of `proxy`, only set by `--fastest`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<u32>,
+    /// Number of connections closed, only set with `--close-connections`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_connections: Option<usize>,
+    /// The new GLOBAL chain, only set when switching GLOBAL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<Chain>,
+}
+
+impl Render for SwitchOutcome {
+    fn render_table(&self) {
+        println!();
+        if !self.switched {
+            println!(
+                "{} Keeping '{}' ({}ms), within tolerance of the fastest",
+                "ℹ".bright_blue(),
+                self.proxy.bright_green(),
+                self.delay.unwrap_or_default()
+            );
+            return;
+        }
+
+        match self.delay {
+            Some(delay) => println!(
+                "{} Switched '{}' to '{}' ({}ms)",
+                "✓".bright_green().bold(),
+                self.group.bright_cyan(),
+                self.proxy.bright_green(),
+                delay
+            ),
+            None => println!(
+                "{} Switched '{}' to '{}'",
+                "✓".bright_green().bold(),
+                self.group.bright_cyan(),
+                self.proxy.bright_green()
+            ),
+        }
+
+        if let Some(closed) = self.closed_connections {
+            println!(
+                "{} Closed {} connection(s) through '{}'",
+                "✓".bright_green().bold(),
+                closed,
+                self.group.bright_cyan()
+            );
+        }
+
+        // Show the new chain if switching GLOBAL
+        if let Some(chain) = &self.chain {
+            println!();
+            println!("{}", "New proxy chain:".bright_yellow());
+            chain.print_tree(false);
+        }
+    }
+
+    fn render_plain(&self) {
+        println!("{}\t{}", self.group, self.proxy);
+    }
+}
+
+pub fn execute(
+    client: &impl ClashApi,
+    group: Option<String>,
+    proxy: Option<String>,
+    close_connections: bool,
+    format: OutputFormat,
+) -> Result<()> {
     let proxies = client.get_proxies()?;
 
     // Get all selector groups
     let mut groups: Vec<_> = proxies
         .proxies
         .iter()
-        .filter(|(_, info)| info.proxy_type == "Selector")
+        .filter(|(_, info)| info.proxy_type.is_selectable())
         .collect();
     groups.sort_by_key(|(name, _)| *name);
 
@@ -19,9 +113,13 @@ pub fn execute(client: &ClashClient, group: Option<String>, proxy: Option<String
         anyhow::bail!("No selector proxy groups found");
     }
 
+    if format != OutputFormat::Table && (group.is_none() || proxy.is_none()) {
+        anyhow::bail!("Group and proxy arguments are required with --output json or plain");
+    }
+
     // Determine the group to switch
     let selected_group = if let Some(g) = group {
-        g
+        resolve_group(&proxies.proxies, &g, format)?
     } else {
         // Interactive mode: select group
         println!("\n{}", "Select Proxy Group:".bright_yellow().bold());
@@ -47,13 +145,9 @@ pub fn execute(client: &ClashClient, group: Option<String>, proxy: Option<String
 
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
-        let group_idx: usize = input.trim().parse().context("Invalid number")?;
+        let group_idx = parse_choice(&input, groups.len(), "group")?;
 
-        if group_idx == 0 || group_idx > groups.len() {
-            anyhow::bail!("Invalid group number");
-        }
-
-        groups[group_idx - 1].0.to_string()
+        groups[group_idx].0.to_string()
     };
 
     // Get the selected group's info
@@ -62,36 +156,23 @@ pub fn execute(client: &ClashClient, group: Option<String>, proxy: Option<String
         .context("Failed to get proxy group info")?;
 
     // Check if it's a selector
-    if proxy_info.proxy_type != "Selector" {
-        anyhow::bail!(
+    if !proxy_info.proxy_type.is_selectable() {
+        return Err(ClashError::InvalidSelection(format!(
             "Cannot switch '{}': not a Selector (type: {})",
-            selected_group,
-            proxy_info.proxy_type
-        );
+            selected_group, proxy_info.proxy_type
+        ))
+        .into());
     }
 
     // Determine the proxy to switch to
     let selected_proxy = if let Some(p) = proxy {
-        // Check if the target proxy is in the available list
-        if !proxy_info.all.contains(&p) {
-            println!(
-                "{} '{}' is not available in group '{}'",
-                "Error:".red().bold(),
-                p,
-                selected_group
-            );
-            println!("\n{}:", "Available proxies".bright_yellow());
-            for (i, name) in proxy_info.all.iter().enumerate() {
-                let marker = if name == &proxy_info.now {
-                    "●".bright_green()
-                } else {
-                    "○".normal()
-                };
-                println!("  {} {}. {}", marker, i + 1, name);
-            }
-            anyhow::bail!("Invalid proxy selection");
-        }
-        p
+        resolve_name(
+            ("proxy", "proxies"),
+            &p,
+            &proxy_info.all,
+            &proxy_info.now,
+            format,
+        )?
     } else {
         // Interactive mode: select proxy
         println!();
@@ -122,61 +203,472 @@ pub fn execute(client: &ClashClient, group: Option<String>, proxy: Option<String
 
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
-        let proxy_idx: usize = input.trim().parse().context("Invalid number")?;
+        let proxy_idx = parse_choice(&input, proxy_info.all.len(), "proxy")?;
 
-        if proxy_idx == 0 || proxy_idx > proxy_info.all.len() {
-            anyhow::bail!("Invalid proxy number");
-        }
-
-        proxy_info.all[proxy_idx - 1].clone()
+        proxy_info.all[proxy_idx].clone()
     };
 
     // Perform the switch
     client.switch_proxy(&selected_group, &selected_proxy)?;
 
-    println!();
-    println!(
-        "{} Switched '{}' to '{}'",
-        "✓".bright_green().bold(),
-        selected_group.bright_cyan(),
-        selected_proxy.bright_green()
-    );
+    let closed_connections = if close_connections {
+        Some(close_group_connections(client, &selected_group)?)
+    } else {
+        None
+    };
 
-    // Show the new chain if switching GLOBAL
-    if selected_group == "GLOBAL" {
-        println!();
+    let chain = if selected_group == "GLOBAL" {
         let proxies = client.get_proxies()?;
-        if let Some(global) = proxies.proxies.get("GLOBAL") {
-            println!("{}", "New proxy chain:".bright_yellow());
-            let mut chain = vec!["GLOBAL".to_string()];
-            let mut current_proxy = global;
+        Some(Chain::resolve(&proxies.proxies, "GLOBAL"))
+    } else {
+        None
+    };
+
+    let outcome = SwitchOutcome {
+        group: selected_group,
+        previous: proxy_info.now,
+        proxy: selected_proxy,
+        switched: true,
+        delay: None,
+        closed_connections,
+        chain,
+    };
+    output::print(&outcome, format)
+}
+
+pub fn execute_fastest(
+    client: &impl ClashApi,
+    group: &str,
+    options: &FastestOptions,
+    close_connections: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let proxies = client.get_proxies()?;
+    let group = resolve_group(&proxies.proxies, group, format)?;
+    let group = group.as_str();
+    let proxy_info = &proxies.proxies[group];
 
-            while !current_proxy.now.is_empty() {
-                let next_name = &current_proxy.now;
-                chain.push(next_name.clone());
+    if !proxy_info.proxy_type.is_selectable() {
+        return Err(ClashError::InvalidSelection(format!(
+            "Cannot switch '{}': not a Selector (type: {})",
+            group, proxy_info.proxy_type
+        ))
+        .into());
+    }
+
+    // DIRECT would win every delay test without going through a proxy
+    let exclude = options.exclude.as_ref().map(|e| e.to_lowercase());
+    let candidates: Vec<String> = proxy_info
+        .all
+        .iter()
+        .filter(|name| {
+            !proxies
+                .proxies
+                .get(*name)
+                .is_some_and(|info| info.proxy_type.is_builtin())
+        })
+        .filter(|name| match &exclude {
+            Some(pattern) => !name.to_lowercase().contains(pattern),
+            None => true,
+        })
+        .cloned()
+        .collect();
+
+    if candidates.is_empty() {
+        anyhow::bail!("No candidate proxies left in '{}' after exclusion", group);
+    }
+
+    if format == OutputFormat::Table {
+        println!(
+            "{} {} candidates in {}...",
+            "Testing".bright_yellow(),
+            candidates.len(),
+            group.bright_cyan()
+        );
+    }
 
-                if let Some(next_proxy) = proxies.proxies.get(next_name) {
-                    if next_proxy.proxy_type == "Selector" || next_proxy.proxy_type == "URLTest" {
-                        current_proxy = next_proxy;
-                    } else {
+    let results = test_delays(client, &candidates, options)?;
+
+    let Some(best) = pick_fastest(&results, &proxy_info.now, options.tolerance) else {
+        anyhow::bail!("No healthy proxy found in '{}'", group);
+    };
+    let best_delay = results
+        .iter()
+        .find(|(name, _)| name == best)
+        .and_then(|(_, delay)| *delay)
+        .unwrap_or_default();
+
+    let switched = best != proxy_info.now;
+    if switched {
+        client.switch_proxy(group, best)?;
+    }
+
+    let closed_connections = if switched && close_connections {
+        Some(close_group_connections(client, group)?)
+    } else {
+        None
+    };
+
+    let outcome = SwitchOutcome {
+        group: group.to_string(),
+        previous: proxy_info.now.clone(),
+        proxy: best.to_string(),
+        switched,
+        delay: Some(best_delay),
+        closed_connections,
+        chain: None,
+    };
+    output::print(&outcome, format)
+}
+
+/// Resolve a group argument. An exact name of any proxy is taken as-is so
+/// that non-Selector groups get a clear error; otherwise the name is matched
+/// against Selector groups in the order of the interactive list.
+fn resolve_group(
+    proxies: &HashMap<String, ProxyInfo>,
+    query: &str,
+    format: OutputFormat,
+) -> Result<String> {
+    if proxies.contains_key(query) {
+        return Ok(query.to_string());
+    }
+
+    let mut groups: Vec<String> = proxies
+        .iter()
+        .filter(|(_, info)| info.proxy_type.is_selectable())
+        .map(|(name, _)| name.clone())
+        .collect();
+    groups.sort();
+
+    resolve_name(("group", "groups"), query, &groups, "", format)
+}
+
+/// Resolve a user-supplied name with `match_name`. When several candidates
+/// match, asks which one was meant if a prompt is possible, and otherwise
+/// fails with the list of candidates.
+fn resolve_name(
+    (kind, kinds): (&str, &str),
+    query: &str,
+    candidates: &[String],
+    current: &str,
+    format: OutputFormat,
+) -> Result<String> {
+    let print_candidates = |names: &[&str]| {
+        for name in names {
+            let marker = if *name == current {
+                "●".bright_green()
+            } else {
+                "○".normal()
+            };
+            let idx = candidates.iter().position(|c| c == name).unwrap_or(0) + 1;
+            eprintln!("  {} {}. {}", marker, idx, name);
+        }
+    };
+
+    match match_name(query, candidates) {
+        NameMatch::Unique(name) => Ok(name.to_string()),
+        NameMatch::NotFound => {
+            eprintln!("{} No {} matches '{}'", "Error:".red().bold(), kind, query);
+            eprintln!("\n{}:", format!("Available {}", kinds).bright_yellow());
+            let all: Vec<&str> = candidates.iter().map(String::as_str).collect();
+            print_candidates(&all);
+            Err(ClashError::NotFound(format!("{} '{}'", kind, query)).into())
+        }
+        NameMatch::Ambiguous(matches)
+            if format == OutputFormat::Table && io::stdin().is_terminal() =>
+        {
+            println!();
+            println!(
+                "{} '{}' matches {} {}:",
+                "?".bright_yellow().bold(),
+                query,
+                matches.len(),
+                kinds
+            );
+            println!();
+            for (idx, name) in matches.iter().enumerate() {
+                println!("  {} {}", format!("[{}]", idx + 1).bright_cyan(), name);
+            }
+            println!();
+            print!("{} ", format!("Enter {} number:", kind).bright_yellow());
+            io::stdout().flush()?;
+
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            let idx = parse_choice(&input, matches.len(), kind)?;
+            Ok(matches[idx].to_string())
+        }
+        NameMatch::Ambiguous(matches) => {
+            eprintln!(
+                "{} '{}' matches {} {}:",
+                "Error:".red().bold(),
+                query,
+                matches.len(),
+                kinds
+            );
+            print_candidates(&matches);
+            Err(ClashError::InvalidSelection(format!(
+                "Ambiguous {} '{}'. Be more specific or use the number.",
+                kind, query
+            ))
+            .into())
+        }
+    }
+}
+
+/// Parse a 1-based choice typed at a prompt offering `count` entries into
+/// a 0-based index
+fn parse_choice(input: &str, count: usize, kind: &str) -> Result<usize> {
+    match input.trim().parse::<usize>() {
+        Ok(idx) if idx >= 1 && idx <= count => Ok(idx - 1),
+        _ => Err(ClashError::InvalidSelection(format!(
+            "Invalid {} number '{}', expected 1 to {}",
+            kind,
+            input.trim(),
+            count
+        ))
+        .into()),
+    }
+}
+
+/// Close every connection whose chain passes through `group`, so long-lived
+/// sockets stop using the previously selected node. Returns how many were
+/// closed.
+fn close_group_connections(client: &impl ClashApi, group: &str) -> Result<usize> {
+    let filter = ConnectionFilter {
+        chain: Some(group.to_string()),
+        ..Default::default()
+    };
+    let response = client.get_connections()?;
+    let matched: Vec<_> = response
+        .connections
+        .iter()
+        .filter(|conn| filter.matches(conn))
+        .collect();
+
+    Ok(connections::close_connections(client, &matched))
+}
+
+/// Delay-test `names` using at most `options.workers` concurrent requests.
+/// Timed-out tests are reported as `None`; any other error, such as a
+/// rejected secret, stops the remaining tests and is returned.
+fn test_delays(
+    client: &impl ClashApi,
+    names: &[String],
+    options: &FastestOptions,
+) -> crate::error::Result<Vec<(String, Option<u32>)>> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(names.len()));
+    let error = Mutex::new(None);
+    let workers = options.workers.clamp(1, names.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(name) = names.get(idx) else {
                         break;
+                    };
+                    match client.get_proxy_delay(name, &options.url, options.timeout, None) {
+                        Ok(delay) => results.lock().unwrap().push((name.clone(), delay)),
+                        Err(e) => {
+                            error.lock().unwrap().get_or_insert(e);
+                            // Let the other workers run out of names
+                            next.store(names.len(), Ordering::Relaxed);
+                            break;
+                        }
                     }
-                } else {
-                    break;
                 }
-            }
+            });
+        }
+    });
 
-            for (i, name) in chain.iter().enumerate() {
-                if i == 0 {
-                    println!("  {} {}", "┌─".bright_blue(), name.bright_cyan());
-                } else if i == chain.len() - 1 {
-                    println!("  {} {}", "└─→".bright_green(), name.bright_green().bold());
-                } else {
-                    println!("  {} {}", "├─→".bright_blue(), name.bright_cyan());
-                }
-            }
+    match error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(results.into_inner().unwrap()),
+    }
+}
+
+/// Pick the lowest-latency healthy node, preferring `current` when it is
+/// within `tolerance` milliseconds of the fastest one.
+fn pick_fastest<'a>(
+    results: &'a [(String, Option<u32>)],
+    current: &str,
+    tolerance: u32,
+) -> Option<&'a str> {
+    let (best, best_delay) = results
+        .iter()
+        .filter_map(|(name, delay)| delay.map(|d| (name, d)))
+        .min_by(|(a_name, a), (b_name, b)| a.cmp(b).then_with(|| a_name.cmp(b_name)))?;
+
+    let current_delay = results
+        .iter()
+        .find(|(name, _)| name == current)
+        .and_then(|(_, delay)| *delay);
+
+    match current_delay {
+        Some(d) if d <= best_delay.saturating_add(tolerance) => results
+            .iter()
+            .find(|(name, _)| name == current)
+            .map(|(n, _)| n.as_str()),
+        _ => Some(best.as_str()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeClash;
+
+    fn results(items: &[(&str, Option<u32>)]) -> Vec<(String, Option<u32>)> {
+        items.iter().map(|(n, d)| (n.to_string(), *d)).collect()
+    }
+
+    #[test]
+    fn test_pick_fastest_lowest_delay() {
+        let r = results(&[("a", Some(300)), ("b", Some(120)), ("c", None)]);
+        assert_eq!(pick_fastest(&r, "a", 0), Some("b"));
+    }
+
+    #[test]
+    fn test_pick_fastest_keeps_current_within_tolerance() {
+        let r = results(&[("a", Some(130)), ("b", Some(120))]);
+        assert_eq!(pick_fastest(&r, "a", 20), Some("a"));
+        assert_eq!(pick_fastest(&r, "a", 5), Some("b"));
+    }
+
+    #[test]
+    fn test_pick_fastest_current_timed_out() {
+        let r = results(&[("a", None), ("b", Some(500))]);
+        assert_eq!(pick_fastest(&r, "a", 1000), Some("b"));
+    }
+
+    #[test]
+    fn test_pick_fastest_all_timed_out() {
+        let r = results(&[("a", None), ("b", None)]);
+        assert_eq!(pick_fastest(&r, "a", 20), None);
+    }
+
+    fn switch(fake: &FakeClash, group: &str, proxy: &str) -> Result<()> {
+        execute(
+            fake,
+            Some(group.to_string()),
+            Some(proxy.to_string()),
+            false,
+            OutputFormat::Json,
+        )
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("2\n", 3, "proxy").unwrap(), 1);
+        for input in ["0", "4", "x", ""] {
+            let error = parse_choice(input, 3, "proxy").unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<ClashError>(),
+                Some(ClashError::InvalidSelection(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_execute_matches_names() {
+        let fake = FakeClash::new();
+
+        switch(&fake, "prox", "japan 03").unwrap();
+        assert_eq!(fake.now("Proxy"), "🇯🇵 Japan 03 | IPLC x1.5");
+        switch(&fake, "Proxy", "3").unwrap();
+        assert_eq!(fake.now("Proxy"), "🇯🇵 Japan 04");
+
+        let error = |result: Result<()>| result.unwrap_err().downcast::<ClashError>().unwrap();
+        // Ambiguous, and JSON output never prompts
+        assert!(matches!(
+            error(switch(&fake, "Proxy", "japan")),
+            ClashError::InvalidSelection(_)
+        ));
+        assert!(matches!(
+            error(switch(&fake, "Proxy", "korea")),
+            ClashError::NotFound(_)
+        ));
+        // URLTest groups can't be switched
+        assert!(matches!(
+            error(switch(&fake, "Auto", "HK")),
+            ClashError::InvalidSelection(_)
+        ));
+        assert_eq!(fake.state.lock().unwrap().switches.len(), 2);
+    }
+
+    #[test]
+    fn test_execute_closes_group_connections() {
+        let fake = FakeClash::new();
+        execute(
+            &fake,
+            Some("Proxy".to_string()),
+            Some("Auto".to_string()),
+            true,
+            OutputFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(fake.state.lock().unwrap().closed_connections, vec!["c1"]);
+    }
+
+    #[test]
+    fn test_execute_fastest() {
+        let fake = FakeClash::new();
+        let mut options = FastestOptions {
+            url: "http://example.com".to_string(),
+            timeout: 100,
+            exclude: None,
+            tolerance: 20,
+            workers: 4,
+        };
+
+        execute_fastest(&fake, "Proxy", &options, false, OutputFormat::Json).unwrap();
+        assert_eq!(fake.now("Proxy"), "🇯🇵 Japan 03 | IPLC x1.5");
+
+        options.exclude = Some("IPLC".to_string());
+        execute_fastest(&fake, "Proxy", &options, false, OutputFormat::Json).unwrap();
+        assert_eq!(fake.now("Proxy"), "🇭🇰 HK 01");
+    }
+
+    #[test]
+    fn test_execute_fastest_skips_direct() {
+        let fake = FakeClash::new();
+        {
+            let mut state = fake.state.lock().unwrap();
+            state.delays.insert("DIRECT".to_string(), 1);
+            state.delays.insert("Streaming".to_string(), 90);
         }
+        let options = FastestOptions {
+            url: "http://example.com".to_string(),
+            timeout: 100,
+            exclude: None,
+            tolerance: 0,
+            workers: 4,
+        };
+
+        execute_fastest(&fake, "GLOBAL", &options, false, OutputFormat::Json).unwrap();
+        assert_eq!(fake.now("GLOBAL"), "Streaming");
     }
 
-    Ok(())
+    #[test]
+    fn test_execute_fastest_surfaces_errors() {
+        let fake = FakeClash::new();
+        fake.state.lock().unwrap().delay_status = Some(reqwest::StatusCode::UNAUTHORIZED);
+        let options = FastestOptions {
+            url: "http://example.com".to_string(),
+            timeout: 100,
+            exclude: None,
+            tolerance: 0,
+            workers: 4,
+        };
+
+        let error = execute_fastest(&fake, "Proxy", &options, false, OutputFormat::Json)
+            .unwrap_err()
+            .downcast::<ClashError>()
+            .unwrap();
+        assert!(matches!(error, ClashError::Unauthorized(_)));
+        assert!(fake.state.lock().unwrap().switches.is_empty());
+    }
 }
@@ -0,0 +1,76 @@
+use crate::api::ClashApi;
+use crate::utils::{format_rate, pad_string};
+use anyhow::Result;
+use colored::Colorize;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+pub fn execute(client: &impl ClashApi, once: bool, duration: Option<u64>) -> Result<()> {
+    let stream = client.stream_traffic()?;
+
+    // Scripted runs print one plain line per sample; an interactive terminal
+    // gets a single line that is redrawn in place
+    let live = !once && duration.is_none() && io::stdout().is_terminal();
+    let deadline = duration.map(|secs| Instant::now() + Duration::from_secs(secs));
+
+    let mut samples = 0u64;
+    let mut total_up = 0u64;
+    let mut total_down = 0u64;
+    let mut peak_up = 0u64;
+    let mut peak_down = 0u64;
+
+    if live {
+        println!(
+            "{} (Ctrl-C to stop)\n",
+            "Live Traffic".bright_yellow().bold()
+        );
+    }
+
+    for traffic in stream {
+        let traffic = traffic?;
+
+        samples += 1;
+        total_up += traffic.up;
+        total_down += traffic.down;
+        peak_up = peak_up.max(traffic.up);
+        peak_down = peak_down.max(traffic.down);
+
+        if live {
+            print!(
+                "\r  {} {}  {} {}",
+                "↑".bright_green(),
+                pad_string(&format_rate(traffic.up), 12),
+                "↓".bright_cyan(),
+                pad_string(&format_rate(traffic.down), 12)
+            );
+            io::stdout().flush()?;
+        } else {
+            println!(
+                "up {} down {}",
+                format_rate(traffic.up),
+                format_rate(traffic.down)
+            );
+        }
+
+        if once || deadline.is_some_and(|d| Instant::now() >= d) {
+            break;
+        }
+    }
+
+    if live {
+        println!();
+    }
+
+    if duration.is_some() && samples > 0 {
+        println!(
+            "avg up {} down {}, peak up {} down {} ({} samples)",
+            format_rate(total_up / samples),
+            format_rate(total_down / samples),
+            format_rate(peak_up),
+            format_rate(peak_down),
+            samples
+        );
+    }
+
+    Ok(())
+}
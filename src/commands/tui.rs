@@ -0,0 +1,676 @@
+use crate::api::ClashApi;
+use crate::error::ClashError;
+use crate::models::{ProxiesResponse, ProxyInfo};
+use crate::utils::{fuzzy_match, pad_string};
+use anyhow::Result;
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, Scope};
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
+
+/// Settings for the `tui` command
+pub struct TuiOptions {
+    pub test_url: String,
+    pub timeout: u32,
+    /// How often proxy state is reloaded from the controller
+    pub refresh: Duration,
+}
+
+/// Results of background requests, sent back to the UI thread
+enum Message {
+    Proxies(Result<ProxiesResponse, ClashError>),
+    Delay {
+        name: String,
+        delay: Result<Option<u32>, ClashError>,
+    },
+    GroupDelay {
+        group: String,
+        members: Vec<String>,
+        delays: Result<HashMap<String, u32>, ClashError>,
+    },
+    Switched {
+        group: String,
+        proxy: String,
+        result: Result<(), ClashError>,
+    },
+}
+
+/// What a key press asks the event loop to do
+#[derive(Debug, PartialEq)]
+enum Action {
+    None,
+    Quit,
+    Refresh,
+    TestNode(String),
+    TestGroup(String),
+    Switch { group: String, proxy: String },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Focus {
+    Groups,
+    Members,
+}
+
+struct App {
+    proxies: HashMap<String, ProxyInfo>,
+    /// Group names, GLOBAL first
+    groups: Vec<String>,
+    group_state: ListState,
+    member_state: ListState,
+    focus: Focus,
+    search: String,
+    searching: bool,
+    /// Results of tests run in this session; `None` is a timeout
+    delays: HashMap<String, Option<u32>>,
+    testing: HashSet<String>,
+    refreshing: bool,
+    status: String,
+}
+
+impl App {
+    fn new(proxies: HashMap<String, ProxyInfo>) -> Self {
+        let mut app = Self {
+            proxies: HashMap::new(),
+            groups: Vec::new(),
+            group_state: ListState::default(),
+            member_state: ListState::default(),
+            focus: Focus::Groups,
+            search: String::new(),
+            searching: false,
+            delays: HashMap::new(),
+            testing: HashSet::new(),
+            refreshing: false,
+            status: String::new(),
+        };
+        app.set_proxies(proxies);
+        app.select_group(0);
+        app
+    }
+
+    /// Replace the proxy state, keeping the selected group and member
+    fn set_proxies(&mut self, proxies: HashMap<String, ProxyInfo>) {
+        let group = self.selected_group().map(str::to_string);
+        let member = self.selected_member();
+
+        let mut groups: Vec<String> = proxies
+            .iter()
+            .filter(|(_, info)| info.proxy_type.is_group())
+            .map(|(name, _)| name.clone())
+            .collect();
+        groups.sort_by(|a, b| (a != "GLOBAL").cmp(&(b != "GLOBAL")).then(a.cmp(b)));
+        self.groups = groups;
+        self.proxies = proxies;
+
+        let group_idx = group
+            .and_then(|g| self.groups.iter().position(|n| *n == g))
+            .unwrap_or(0);
+        self.group_state
+            .select((!self.groups.is_empty()).then_some(group_idx));
+
+        let members = self.members();
+        let member_idx = member
+            .and_then(|m| members.iter().position(|n| *n == m))
+            .or((!members.is_empty()).then_some(0));
+        self.member_state.select(member_idx);
+    }
+
+    fn selected_group(&self) -> Option<&str> {
+        self.group_state
+            .selected()
+            .and_then(|idx| self.groups.get(idx))
+            .map(String::as_str)
+    }
+
+    /// Members of the selected group matching the search, best match first
+    fn members(&self) -> Vec<&str> {
+        let Some(info) = self.selected_group().and_then(|g| self.proxies.get(g)) else {
+            return Vec::new();
+        };
+
+        let mut members: Vec<(i32, &str)> = info
+            .all
+            .iter()
+            .filter_map(|name| fuzzy_match(&self.search, name).map(|score| (score, name.as_str())))
+            .collect();
+        if !self.search.is_empty() {
+            members.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        }
+        members.into_iter().map(|(_, name)| name).collect()
+    }
+
+    fn selected_member(&self) -> Option<String> {
+        let members = self.members();
+        self.member_state
+            .selected()
+            .and_then(|idx| members.get(idx))
+            .map(|name| name.to_string())
+    }
+
+    /// Select a group and move the member cursor to its current node
+    fn select_group(&mut self, idx: usize) {
+        if self.groups.is_empty() {
+            return;
+        }
+        self.group_state
+            .select(Some(idx.min(self.groups.len() - 1)));
+
+        let now = self
+            .selected_group()
+            .and_then(|g| self.proxies.get(g))
+            .map(|info| info.now.clone())
+            .unwrap_or_default();
+        let members = self.members();
+        let idx = members.iter().position(|m| *m == now).unwrap_or(0);
+        self.member_state
+            .select((!members.is_empty()).then_some(idx));
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        match self.focus {
+            Focus::Groups => {
+                let current = self.group_state.selected().unwrap_or(0);
+                self.select_group(current.saturating_add_signed(delta));
+            }
+            Focus::Members => {
+                let len = self.members().len();
+                if len == 0 {
+                    return;
+                }
+                let current = self.member_state.selected().unwrap_or(0);
+                self.member_state
+                    .select(Some(current.saturating_add_signed(delta).min(len - 1)));
+            }
+        }
+    }
+
+    fn set_search(&mut self, search: String) {
+        self.search = search;
+        let len = self.members().len();
+        self.member_state.select((len > 0).then_some(0));
+    }
+
+    /// Latest known latency: a test from this session, else the controller history
+    fn delay(&self, name: &str) -> Option<Option<u32>> {
+        if let Some(delay) = self.delays.get(name) {
+            return Some(*delay);
+        }
+        self.proxies
+            .get(name)
+            .and_then(|info| info.history.last())
+            .map(|item| (item.delay > 0).then_some(item.delay))
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if self.searching {
+            match key.code {
+                KeyCode::Esc => {
+                    self.searching = false;
+                    self.set_search(String::new());
+                }
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Backspace => {
+                    let mut search = self.search.clone();
+                    search.pop();
+                    self.set_search(search);
+                }
+                KeyCode::Up => self.move_cursor(-1),
+                KeyCode::Down => self.move_cursor(1),
+                KeyCode::Char(c) => {
+                    let search = format!("{}{}", self.search, c);
+                    self.set_search(search);
+                }
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        self.status.clear();
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Action::Quit;
+            }
+            KeyCode::Esc if !self.search.is_empty() => self.set_search(String::new()),
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Groups => Focus::Members,
+                    Focus::Members => Focus::Groups,
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Groups,
+            KeyCode::Right | KeyCode::Char('l') => self.focus = Focus::Members,
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-10),
+            KeyCode::PageDown => self.move_cursor(10),
+            KeyCode::Home | KeyCode::Char('g') => self.move_cursor(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_cursor(isize::MAX),
+            KeyCode::Char('/') => {
+                self.focus = Focus::Members;
+                self.searching = true;
+            }
+            KeyCode::Char('r') => return Action::Refresh,
+            KeyCode::Char('t') if self.focus == Focus::Members => {
+                if let Some(member) = self.selected_member() {
+                    return Action::TestNode(member);
+                }
+            }
+            KeyCode::Char('t') | KeyCode::Char('T') => {
+                if let Some(group) = self.selected_group() {
+                    return Action::TestGroup(group.to_string());
+                }
+            }
+            KeyCode::Enter if self.focus == Focus::Groups => self.focus = Focus::Members,
+            KeyCode::Enter => {
+                let (Some(group), Some(proxy)) = (self.selected_group(), self.selected_member())
+                else {
+                    return Action::None;
+                };
+                let Some(info) = self.proxies.get(group) else {
+                    return Action::None;
+                };
+                if !info.proxy_type.is_selectable() {
+                    self.status = format!(
+                        "'{}' is a {} group; only Selector groups can be switched",
+                        group, info.proxy_type
+                    );
+                    return Action::None;
+                }
+                return Action::Switch {
+                    group: group.to_string(),
+                    proxy,
+                };
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Proxies(Ok(proxies)) => {
+                self.refreshing = false;
+                self.set_proxies(proxies.proxies);
+            }
+            Message::Proxies(Err(e)) => {
+                self.refreshing = false;
+                self.status = format!("Refresh failed: {:#}", e);
+            }
+            Message::Delay { name, delay } => {
+                self.testing.remove(&name);
+                match delay {
+                    Ok(delay) => {
+                        self.delays.insert(name, delay);
+                    }
+                    Err(e) => self.status = format!("Test of '{}' failed: {:#}", name, e),
+                }
+            }
+            Message::GroupDelay {
+                group,
+                members,
+                delays,
+            } => {
+                for member in &members {
+                    self.testing.remove(member);
+                }
+                match delays {
+                    Ok(delays) => {
+                        for member in members {
+                            let delay = delays.get(&member).copied();
+                            self.delays.insert(member, delay);
+                        }
+                        self.status = format!("Tested {} node(s) in '{}'", delays.len(), group);
+                    }
+                    Err(e) => self.status = format!("Test of '{}' failed: {:#}", group, e),
+                }
+            }
+            Message::Switched {
+                group,
+                proxy,
+                result,
+            } => match result {
+                Ok(()) => {
+                    if let Some(info) = self.proxies.get_mut(&group) {
+                        info.now = proxy.clone();
+                    }
+                    self.status = format!("Switched '{}' to '{}'", group, proxy);
+                }
+                Err(e) => self.status = format!("Switch failed: {:#}", e),
+            },
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+
+        self.draw_groups(frame, left);
+        self.draw_members(frame, right);
+
+        let footer_text = if self.searching {
+            Line::from(vec![
+                Span::styled("/", Style::new().fg(Color::Yellow)),
+                Span::raw(self.search.clone()),
+                Span::styled("█", Style::new().fg(Color::DarkGray)),
+            ])
+        } else if !self.status.is_empty() {
+            Line::from(self.status.clone())
+        } else {
+            Line::styled(
+                "↑↓ move  ←→/Tab pane  / search  Enter switch  t test  T test group  r refresh  q quit",
+                Style::new().fg(Color::DarkGray),
+            )
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+
+    fn pane_block(&self, title: String, focus: Focus) -> Block<'static> {
+        let style = if self.focus == focus {
+            Style::new().fg(Color::Cyan)
+        } else {
+            Style::new().fg(Color::DarkGray)
+        };
+        Block::bordered().title(title).border_style(style)
+    }
+
+    fn draw_groups(&mut self, frame: &mut Frame, area: Rect) {
+        let name_width = self
+            .groups
+            .iter()
+            .map(|g| g.width())
+            .max()
+            .unwrap_or(0)
+            .min(24);
+        let items: Vec<ListItem> = self
+            .groups
+            .iter()
+            .map(|name| {
+                let info = &self.proxies[name];
+                ListItem::new(Line::from(vec![
+                    Span::raw(pad_string(name, name_width)),
+                    Span::raw(" "),
+                    Span::styled(info.now.clone(), Style::new().fg(Color::Green)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(self.pane_block(format!(" Groups ({}) ", self.groups.len()), Focus::Groups))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("▶ ");
+        frame.render_stateful_widget(list, area, &mut self.group_state);
+    }
+
+    fn draw_members(&mut self, frame: &mut Frame, area: Rect) {
+        let (group, group_type, now) = self
+            .selected_group()
+            .and_then(|g| {
+                self.proxies
+                    .get(g)
+                    .map(|info| (g.to_string(), info.proxy_type.to_string(), info.now.clone()))
+            })
+            .unwrap_or_default();
+
+        let members = self.members();
+        let name_width = members.iter().map(|m| m.width()).max().unwrap_or(0).min(40);
+        let items: Vec<ListItem> = members
+            .iter()
+            .map(|name| {
+                let marker = if *name == now { "● " } else { "  " };
+                let node_type = self
+                    .proxies
+                    .get(*name)
+                    .map(|info| info.proxy_type.as_str())
+                    .unwrap_or_default();
+                let delay = if self.testing.contains(*name) {
+                    Span::styled("testing…", Style::new().fg(Color::DarkGray))
+                } else {
+                    match self.delay(name) {
+                        None => Span::styled("-", Style::new().fg(Color::DarkGray)),
+                        Some(None) => Span::styled("timeout", Style::new().fg(Color::Red)),
+                        Some(Some(ms)) => {
+                            let color = match ms {
+                                0..200 => Color::Green,
+                                200..500 => Color::Yellow,
+                                _ => Color::Red,
+                            };
+                            Span::styled(format!("{}ms", ms), Style::new().fg(color))
+                        }
+                    }
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(marker, Style::new().fg(Color::Green)),
+                    Span::raw(pad_string(name, name_width)),
+                    Span::raw(" "),
+                    Span::styled(pad_string(node_type, 12), Style::new().fg(Color::Blue)),
+                    delay,
+                ]))
+            })
+            .collect();
+
+        let mut title = format!(" {} [{}] ({}) ", group, group_type, members.len());
+        if !self.search.is_empty() {
+            title = format!("{}/{} ", title, self.search);
+        }
+        if self.refreshing {
+            title.push_str("⟳ ");
+        }
+
+        let list = List::new(items)
+            .block(self.pane_block(title, Focus::Members))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("▶ ");
+        frame.render_stateful_widget(list, area, &mut self.member_state);
+    }
+}
+
+fn spawn_refresh<'scope, C: ClashApi>(
+    scope: &'scope Scope<'scope, '_>,
+    client: &'scope C,
+    tx: &Sender<Message>,
+) {
+    let tx = tx.clone();
+    scope.spawn(move || {
+        let _ = tx.send(Message::Proxies(client.get_proxies()));
+    });
+}
+
+fn run<'scope, C: ClashApi>(
+    terminal: &mut DefaultTerminal,
+    scope: &'scope Scope<'scope, '_>,
+    client: &'scope C,
+    options: &'scope TuiOptions,
+    mut app: App,
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut last_refresh = Instant::now();
+
+    loop {
+        while let Ok(message) = rx.try_recv() {
+            let switched = matches!(message, Message::Switched { result: Ok(()), .. });
+            app.handle_message(message);
+            // Pick up the new chain and any URLTest reselection
+            if switched && !app.refreshing {
+                app.refreshing = true;
+                spawn_refresh(scope, client, &tx);
+            }
+        }
+
+        if last_refresh.elapsed() >= options.refresh && !app.refreshing {
+            app.refreshing = true;
+            last_refresh = Instant::now();
+            spawn_refresh(scope, client, &tx);
+        }
+
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if !event::poll(Duration::from_millis(200))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match app.handle_key(key) {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Refresh => {
+                if !app.refreshing {
+                    app.refreshing = true;
+                    last_refresh = Instant::now();
+                    spawn_refresh(scope, client, &tx);
+                }
+            }
+            Action::TestNode(name) => {
+                if app.testing.insert(name.clone()) {
+                    let tx = tx.clone();
+                    scope.spawn(move || {
+                        let delay =
+                            client.get_proxy_delay(&name, &options.test_url, options.timeout, None);
+                        let _ = tx.send(Message::Delay { name, delay });
+                    });
+                }
+            }
+            Action::TestGroup(group) => {
+                let members = app.proxies[&group].all.clone();
+                app.testing.extend(members.iter().cloned());
+                app.status = format!("Testing '{}'...", group);
+                let tx = tx.clone();
+                scope.spawn(move || {
+                    let delays =
+                        client.get_group_delay(&group, &options.test_url, options.timeout, None);
+                    let _ = tx.send(Message::GroupDelay {
+                        group,
+                        members,
+                        delays,
+                    });
+                });
+            }
+            Action::Switch { group, proxy } => {
+                let tx = tx.clone();
+                scope.spawn(move || {
+                    let result = client.switch_proxy(&group, &proxy);
+                    let _ = tx.send(Message::Switched {
+                        group,
+                        proxy,
+                        result,
+                    });
+                });
+            }
+        }
+    }
+}
+
+pub fn execute(client: &impl ClashApi, options: &TuiOptions) -> Result<()> {
+    // Fail before taking over the terminal if the controller is unreachable
+    let proxies = client.get_proxies()?;
+    let app = App::new(proxies.proxies);
+
+    let mut terminal = ratatui::init();
+    thread::scope(|scope| {
+        let result = run(&mut terminal, scope, client, options, app);
+        // Restore before the scope waits for in-flight latency tests
+        ratatui::restore();
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProxyType;
+
+    fn proxy(proxy_type: &str, all: &[&str], now: &str) -> ProxyInfo {
+        ProxyInfo {
+            proxy_type: ProxyType::from(proxy_type.to_string()),
+            all: all.iter().map(|s| s.to_string()).collect(),
+            now: now.to_string(),
+            history: Vec::new(),
+        }
+    }
+
+    fn app() -> App {
+        let mut proxies = HashMap::new();
+        proxies.insert(
+            "GLOBAL".to_string(),
+            proxy("Selector", &["Proxy", "DIRECT"], "Proxy"),
+        );
+        proxies.insert(
+            "Proxy".to_string(),
+            proxy(
+                "Selector",
+                &["HK 01", "JP Tokyo 02", "US West"],
+                "JP Tokyo 02",
+            ),
+        );
+        proxies.insert(
+            "Auto".to_string(),
+            proxy("URLTest", &["HK 01", "US West"], "HK 01"),
+        );
+        for node in ["HK 01", "JP Tokyo 02", "US West"] {
+            proxies.insert(node.to_string(), proxy("Shadowsocks", &[], ""));
+        }
+        proxies.insert("DIRECT".to_string(), proxy("Direct", &[], ""));
+        App::new(proxies)
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_app_groups_and_selection() {
+        let mut app = app();
+        assert_eq!(app.groups, vec!["GLOBAL", "Auto", "Proxy"]);
+        assert_eq!(app.selected_group(), Some("GLOBAL"));
+
+        app.handle_key(key(KeyCode::End));
+        assert_eq!(app.selected_group(), Some("Proxy"));
+        // Cursor starts on the current node
+        assert_eq!(app.selected_member().as_deref(), Some("JP Tokyo 02"));
+    }
+
+    #[test]
+    fn test_app_search_and_switch() {
+        let mut app = app();
+        app.handle_key(key(KeyCode::End));
+        app.handle_key(key(KeyCode::Char('/')));
+        for c in "us".chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+        app.handle_key(key(KeyCode::Enter));
+        assert_eq!(app.members(), vec!["US West"]);
+        assert_eq!(
+            app.handle_key(key(KeyCode::Enter)),
+            Action::Switch {
+                group: "Proxy".to_string(),
+                proxy: "US West".to_string()
+            }
+        );
+
+        // URLTest groups pick their own node
+        app.handle_key(key(KeyCode::Esc));
+        app.handle_key(key(KeyCode::Left));
+        app.handle_key(key(KeyCode::Up));
+        assert_eq!(app.selected_group(), Some("Auto"));
+        app.handle_key(key(KeyCode::Right));
+        assert_eq!(app.handle_key(key(KeyCode::Enter)), Action::None);
+        assert!(app.status.contains("only Selector"));
+    }
+}
@@ -1,12 +1,27 @@
-use crate::client::ClashClient;
+use crate::api::ClashApi;
+use crate::models::Version;
+use crate::output::{self, OutputFormat, Render};
 use anyhow::Result;
 
-pub fn execute(client: &ClashClient) -> Result<()> {
+impl Render for Version {
+    fn render_table(&self) {
+        println!(
+            "Clash Version: {} - {}",
+            self.version,
+            if self.meta { "meta" } else { "non-meta" }
+        );
+    }
+
+    fn render_plain(&self) {
+        println!(
+            "{}\t{}",
+            self.version,
+            if self.meta { "meta" } else { "non-meta" }
+        );
+    }
+}
+
+pub fn execute(client: &impl ClashApi, format: OutputFormat) -> Result<()> {
     let version = client.version()?;
-    println!(
-        "Clash Version: {} - {}",
-        version.version,
-        if version.meta { "meta" } else { "non-meta" }
-    );
-    Ok(())
+    output::print(&version, format)
 }
@@ -0,0 +1,114 @@
+use reqwest::StatusCode;
+use std::process::ExitCode;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, ClashError>;
+
+/// Why a request to the Clash controller failed. Each variant has its own
+/// process exit code so scripts can tell the cases apart.
+#[derive(Debug, Error)]
+pub enum ClashError {
+    /// The request cannot be built, e.g. an empty name or a bad URL
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    /// No response from the controller (refused, DNS, timeout, reset)
+    #[error("Cannot reach the Clash controller at {url}")]
+    Unreachable {
+        url: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The controller's TLS certificate failed verification
+    #[error(
+        "The TLS certificate of {url} was rejected ({reason}), use --ca-cert to trust its CA, --pin-sha256 to accept this one certificate or --insecure to skip verification"
+    )]
+    Certificate { url: String, reason: String },
+
+    /// The controller rejected the secret (401/403)
+    #[error(
+        "The Clash controller rejected the request ({0}), check your secret (--secret, CLASH_SECRET or the profile)"
+    )]
+    Unauthorized(StatusCode),
+
+    /// The named proxy, group, provider or connection does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// The name exists but cannot be chosen, or matches several names
+    #[error("{0}")]
+    InvalidSelection(String),
+
+    /// Any other non-success response
+    #[error("The Clash controller returned {status}: {body}")]
+    Http { status: StatusCode, body: String },
+
+    /// The response does not match the expected format
+    #[error("Failed to parse {what} from the Clash controller")]
+    Decode {
+        what: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl ClashError {
+    /// Exit codes, also listed in the README:
+    ///
+    /// | Code | Meaning |
+    /// |------|---------|
+    /// | 1 | Any other error |
+    /// | 2 | Invalid arguments (also used by clap) |
+    /// | 3 | Controller unreachable |
+    /// | 4 | Unauthorized, wrong or missing secret |
+    /// | 5 | Proxy, group or provider not found |
+    /// | 6 | Invalid or ambiguous selection |
+    /// | 7 | Other HTTP error |
+    /// | 8 | Unexpected response format |
+    /// | 9 | TLS certificate rejected |
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ClashError::InvalidRequest(_) => 2,
+            ClashError::Unreachable { .. } => 3,
+            ClashError::Unauthorized(_) => 4,
+            ClashError::NotFound(_) => 5,
+            ClashError::InvalidSelection(_) => 6,
+            ClashError::Http { .. } => 7,
+            ClashError::Decode { .. } => 8,
+            ClashError::Certificate { .. } => 9,
+        }
+    }
+}
+
+/// Exit code for an error chain: that of the first `ClashError` in it, or 1
+pub fn exit_code(error: &anyhow::Error) -> ExitCode {
+    let code = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<ClashError>())
+        .map_or(1, ClashError::exit_code);
+    ExitCode::from(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_found_through_context() {
+        let error = Err::<(), _>(ClashError::NotFound("Proxy 'x'".to_string()))
+            .context("Failed to switch")
+            .unwrap_err();
+        assert_eq!(exit_code(&error), ExitCode::from(5));
+
+        assert_eq!(exit_code(&anyhow::anyhow!("plain")), ExitCode::from(1));
+    }
+
+    #[test]
+    fn test_unauthorized_mentions_secret() {
+        let message = ClashError::Unauthorized(StatusCode::UNAUTHORIZED).to_string();
+        assert!(message.contains("401"));
+        assert!(message.contains("check your secret"));
+    }
+}
@@ -0,0 +1,312 @@
+//! In-memory `ClashApi` implementation for command tests
+
+use crate::api::{ClashApi, Stream};
+use crate::error::{ClashError, Result};
+use crate::models::*;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Controller state, kept as the JSON the real controller would return
+pub struct State {
+    pub mode: String,
+    pub proxies: Value,
+    pub rules: Value,
+    pub connections: Value,
+    /// Latency returned by delay tests; missing names time out
+    pub delays: HashMap<String, u32>,
+    /// Makes every delay test fail with this status, e.g. 401 for a wrong secret
+    pub delay_status: Option<StatusCode>,
+    /// Every successful `switch_proxy` call as (group, proxy)
+    pub switches: Vec<(String, String)>,
+    pub closed_connections: Vec<String>,
+    pub proxy_providers: Value,
+    pub rule_providers: Value,
+    /// Every successful provider update or health check as "action name"
+    pub provider_actions: Vec<String>,
+}
+
+pub struct FakeClash {
+    pub state: Mutex<State>,
+}
+
+fn parse<T: DeserializeOwned>(value: &Value) -> Result<T> {
+    serde_json::from_value(value.clone()).map_err(|source| ClashError::Decode {
+        what: "fake state".to_string(),
+        source,
+    })
+}
+
+impl FakeClash {
+    /// A controller in Rule mode with this layout:
+    ///
+    /// - GLOBAL (Selector) → Proxy
+    /// - Proxy (Selector) → 🇭🇰 HK 01, also 🇯🇵 Japan 03 | IPLC x1.5, 🇯🇵 Japan 04, Auto
+    /// - Auto (URLTest) → 🇯🇵 Japan 04
+    /// - Streaming (Fallback) → 🇯🇵 Japan 03 | IPLC x1.5
+    ///
+    /// and an HTTP and an Inline provider each for proxies and rules
+    pub fn new() -> Self {
+        let proxies = json!({
+            "GLOBAL": {"type": "Selector", "all": ["Proxy", "Auto", "Streaming", "DIRECT"], "now": "Proxy"},
+            "Proxy": {
+                "type": "Selector",
+                "all": ["🇭🇰 HK 01", "🇯🇵 Japan 03 | IPLC x1.5", "🇯🇵 Japan 04", "Auto"],
+                "now": "🇭🇰 HK 01"
+            },
+            "Auto": {"type": "URLTest", "all": ["🇭🇰 HK 01", "🇯🇵 Japan 04"], "now": "🇯🇵 Japan 04"},
+            "Streaming": {"type": "Fallback", "all": ["🇯🇵 Japan 03 | IPLC x1.5"], "now": "🇯🇵 Japan 03 | IPLC x1.5"},
+            "🇭🇰 HK 01": {"type": "Shadowsocks", "history": [{"time": "2024-01-01T00:00:00Z", "delay": 120}]},
+            "🇯🇵 Japan 03 | IPLC x1.5": {"type": "Vmess"},
+            "🇯🇵 Japan 04": {"type": "Trojan"},
+            "DIRECT": {"type": "Direct"},
+            "REJECT": {"type": "Reject"}
+        });
+        let rules = json!([
+            {"type": "DomainSuffix", "payload": "netflix.com", "proxy": "Streaming"},
+            {"type": "DomainSuffix", "payload": "google.com", "proxy": "Proxy"},
+            {"type": "GeoIP", "payload": "CN", "proxy": "DIRECT"},
+            {"type": "Match", "payload": "", "proxy": "Proxy"}
+        ]);
+        let connections = json!([
+            {
+                "id": "c1",
+                "metadata": {"host": "www.google.com", "destinationPort": "443"},
+                "chains": ["🇭🇰 HK 01", "Proxy"],
+                "rule": "DomainSuffix",
+                "rulePayload": "google.com"
+            },
+            {
+                "id": "c2",
+                "metadata": {"host": "www.baidu.com", "destinationPort": "443"},
+                "chains": ["DIRECT"],
+                "rule": "GeoIP",
+                "rulePayload": "CN"
+            }
+        ]);
+        let delays = [
+            ("🇭🇰 HK 01", 120),
+            ("🇯🇵 Japan 03 | IPLC x1.5", 80),
+            ("🇯🇵 Japan 04", 300),
+        ]
+        .into_iter()
+        .map(|(name, delay)| (name.to_string(), delay))
+        .collect();
+
+        let proxy_providers = json!({
+            "default": {"name": "default", "type": "Proxy", "vehicleType": "Compatible"},
+            "local": {"name": "local", "type": "Proxy", "vehicleType": "Inline"},
+            "subscription": {"name": "subscription", "type": "Proxy", "vehicleType": "HTTP"}
+        });
+        let rule_providers = json!({
+            "lan": {"name": "lan", "behavior": "ipcidr", "vehicleType": "Inline"},
+            "reject": {"name": "reject", "behavior": "domain", "vehicleType": "HTTP"}
+        });
+
+        Self {
+            state: Mutex::new(State {
+                mode: "Rule".to_string(),
+                proxies,
+                rules,
+                connections,
+                delays,
+                delay_status: None,
+                switches: Vec::new(),
+                closed_connections: Vec::new(),
+                proxy_providers,
+                rule_providers,
+                provider_actions: Vec::new(),
+            }),
+        }
+    }
+
+    /// Record `action` on a provider. Like mihomo, only providers fetched
+    /// over HTTP or from a file accept it.
+    fn provider_action(&self, action: &str, kind: &str, name: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let providers = match kind {
+            "proxy" => &state.proxy_providers,
+            _ => &state.rule_providers,
+        };
+        match providers[name]["vehicleType"].as_str() {
+            Some("HTTP" | "File") => {
+                state.provider_actions.push(format!("{} {}", action, name));
+                Ok(())
+            }
+            Some(_) => Err(ClashError::Http {
+                status: StatusCode::BAD_REQUEST,
+                body: format!("{} provider '{}' has no vehicle", kind, name),
+            }),
+            None => Err(ClashError::NotFound(format!(
+                "{} provider '{}'",
+                kind, name
+            ))),
+        }
+    }
+
+    /// The group's current selection
+    pub fn now(&self, group: &str) -> String {
+        let state = self.state.lock().unwrap();
+        state.proxies[group]["now"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+impl ClashApi for FakeClash {
+    fn version(&self) -> Result<Version> {
+        parse(&json!({"meta": true, "version": "fake"}))
+    }
+
+    fn get_proxies(&self) -> Result<ProxiesResponse> {
+        let state = self.state.lock().unwrap();
+        parse(&json!({"proxies": state.proxies}))
+    }
+
+    fn get_proxy(&self, name: &str) -> Result<ProxyInfo> {
+        let state = self.state.lock().unwrap();
+        match state.proxies.get(name) {
+            Some(proxy) => parse(proxy),
+            None => Err(ClashError::NotFound(format!("proxy '{}'", name))),
+        }
+    }
+
+    fn switch_proxy(&self, group: &str, proxy: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let info: ProxyInfo = match state.proxies.get(group) {
+            Some(info) => parse(info)?,
+            None => return Err(ClashError::NotFound(format!("group '{}'", group))),
+        };
+        if !info.proxy_type.is_selectable() {
+            return Err(ClashError::InvalidSelection(format!(
+                "'{}' is not a Selector",
+                group
+            )));
+        }
+        if !info.all.iter().any(|name| name == proxy) {
+            return Err(ClashError::InvalidSelection(format!(
+                "'{}' is not in '{}'",
+                proxy, group
+            )));
+        }
+
+        state.proxies[group]["now"] = json!(proxy);
+        state.switches.push((group.to_string(), proxy.to_string()));
+        Ok(())
+    }
+
+    fn get_proxy_delay(
+        &self,
+        name: &str,
+        _url: &str,
+        _timeout: u32,
+        _expected: Option<&str>,
+    ) -> Result<Option<u32>> {
+        let state = self.state.lock().unwrap();
+        match state.delay_status {
+            Some(status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)) => {
+                Err(ClashError::Unauthorized(status))
+            }
+            Some(status) => Err(ClashError::Http {
+                status,
+                body: String::new(),
+            }),
+            None => Ok(state.delays.get(name).copied()),
+        }
+    }
+
+    fn get_group_delay(
+        &self,
+        group: &str,
+        _url: &str,
+        _timeout: u32,
+        _expected: Option<&str>,
+    ) -> Result<HashMap<String, u32>> {
+        let info = self.get_proxy(group)?;
+        let state = self.state.lock().unwrap();
+        Ok(info
+            .all
+            .into_iter()
+            .filter_map(|name| state.delays.get(&name).map(|delay| (name, *delay)))
+            .collect())
+    }
+
+    fn get_connections(&self) -> Result<ConnectionsResponse> {
+        let state = self.state.lock().unwrap();
+        parse(&json!({"connections": state.connections}))
+    }
+
+    fn close_all_connections(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.connections = json!([]);
+        Ok(())
+    }
+
+    fn close_connection(&self, id: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(connections) = state.connections.as_array_mut() {
+            connections.retain(|conn| conn["id"] != id);
+        }
+        state.closed_connections.push(id.to_string());
+        Ok(())
+    }
+
+    fn get_rules(&self) -> Result<RulesResponse> {
+        let state = self.state.lock().unwrap();
+        parse(&json!({"rules": state.rules}))
+    }
+
+    fn get_proxy_providers(&self) -> Result<ProxyProvidersResponse> {
+        let state = self.state.lock().unwrap();
+        parse(&json!({"providers": state.proxy_providers}))
+    }
+
+    fn get_proxy_provider(&self, name: &str) -> Result<ProxyProvider> {
+        let state = self.state.lock().unwrap();
+        match state.proxy_providers.get(name) {
+            Some(provider) => parse(provider),
+            None => Err(ClashError::NotFound(format!("proxy provider '{}'", name))),
+        }
+    }
+
+    fn update_proxy_provider(&self, name: &str) -> Result<()> {
+        self.provider_action("update", "proxy", name)
+    }
+
+    fn healthcheck_proxy_provider(&self, name: &str) -> Result<()> {
+        self.provider_action("healthcheck", "proxy", name)
+    }
+
+    fn get_rule_providers(&self) -> Result<RuleProvidersResponse> {
+        let state = self.state.lock().unwrap();
+        parse(&json!({"providers": state.rule_providers}))
+    }
+
+    fn update_rule_provider(&self, name: &str) -> Result<()> {
+        self.provider_action("update", "rule", name)
+    }
+
+    fn get_config(&self) -> Result<Config> {
+        let state = self.state.lock().unwrap();
+        parse(&json!({"mode": state.mode}))
+    }
+
+    fn set_mode(&self, mode: &str) -> Result<()> {
+        self.state.lock().unwrap().mode = mode.to_string();
+        Ok(())
+    }
+
+    fn stream_traffic(&self) -> Result<Stream<Traffic>> {
+        let samples: Vec<Result<Traffic>> = vec![parse(&json!({"up": 1024, "down": 2048}))];
+        Ok(Box::new(samples.into_iter()))
+    }
+
+    fn stream_logs(&self, _level: &str) -> Result<Stream<LogEntry>> {
+        let entries: Vec<Result<LogEntry>> =
+            vec![parse(&json!({"type": "info", "payload": "fake log"}))];
+        Ok(Box::new(entries.into_iter()))
+    }
+}
@@ -1,5 +1,13 @@
+pub mod api;
+pub mod chain;
 pub mod cli;
 pub mod client;
 pub mod commands;
+pub mod error;
+#[cfg(test)]
+mod fake;
 pub mod models;
+pub mod output;
+pub mod settings;
+pub mod tls;
 pub mod utils;
@@ -1,31 +1,321 @@
+mod api;
+mod chain;
 mod cli;
 mod client;
 mod commands;
+mod error;
+#[cfg(test)]
+mod fake;
 mod models;
+mod output;
+mod settings;
+mod tls;
 mod utils;
 
 use anyhow::Result;
-use clap::Parser;
-use cli::{Cli, Command};
-use client::ClashClient;
+use clap::{CommandFactory, FromArgMatches};
+use cli::{Cli, Command, ProfileCommand, ProvidersCommand, RuleProvidersCommand, SecretSource};
+use client::{ClashClient, ClientOptions};
+use colored::Colorize;
+use output::OutputFormat;
+use settings::{DEFAULT_TEST_URL, DEFAULT_TIMEOUT, DEFAULT_URL, Profile, Settings};
+use std::process::ExitCode;
+use std::time::Duration;
+use tls::TlsOptions;
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let secret_source = cli.secret_source(&matches);
 
-    let secret = if cli.secret.is_empty() {
-        None
+    match run(cli, secret_source) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            error::exit_code(&e)
+        }
+    }
+}
+
+fn run(cli: Cli, secret_source: Option<SecretSource>) -> Result<()> {
+    if std::env::args().any(|arg| arg == "--secret" || arg.starts_with("--secret=")) {
+        eprintln!(
+            "{} --secret on the command line is visible in shell history and process lists, prefer CLASH_SECRET or --secret-file",
+            "Warning:".yellow().bold()
+        );
+    }
+
+    let cli_secret = match &secret_source {
+        Some(SecretSource::Stdin) => Some(settings::read_secret_stdin()?),
+        Some(SecretSource::File(path)) => Some(settings::read_secret_file(path)?),
+        Some(SecretSource::Value(secret) | SecretSource::Env(secret)) => Some(secret.clone()),
+        None => None,
+    };
+
+    let config_path = cli.config.or_else(Settings::default_path);
+    let format = cli.output;
+
+    if format != OutputFormat::Table && !cli.command.supports_output() {
+        anyhow::bail!("This command only supports --output table");
+    }
+
+    if let Command::Profile { action } = cli.command {
+        let Some(path) = config_path else {
+            anyhow::bail!("Cannot determine the config file location, use --config");
+        };
+        return match action {
+            ProfileCommand::Add {
+                name,
+                group,
+                timeout,
+                test_url,
+                close_connections,
+                default,
+            } => {
+                // A secret file is stored by path so the secret stays out of
+                // the config. A plain secret is only stored when it was given
+                // for this command, never one picked up from CLASH_SECRET.
+                let (secret, secret_file) = match secret_source {
+                    Some(SecretSource::File(path)) => (None, Some(path)),
+                    Some(SecretSource::Stdin | SecretSource::Value(_)) => {
+                        (cli_secret.filter(|s| !s.is_empty()), None)
+                    }
+                    Some(SecretSource::Env(_)) | None => (None, None),
+                };
+                if secret.is_some() {
+                    eprintln!(
+                        "{} The secret is stored in plain text in {}, use --secret-file to store only its path",
+                        "Note:".bright_blue().bold(),
+                        path.display()
+                    );
+                }
+                let profile = Profile {
+                    url: cli.url,
+                    secret,
+                    secret_file: secret_file.map(|p| p.canonicalize().unwrap_or(p)),
+                    group,
+                    timeout,
+                    test_url,
+                    close_connections: close_connections.then_some(true),
+                    connect_timeout: cli.connect_timeout,
+                    read_timeout: cli.read_timeout,
+                    stream_timeout: cli.stream_timeout,
+                    retries: cli.retries,
+                    retry_backoff: cli.retry_backoff,
+                    ca_cert: cli.ca_cert.map(|p| p.canonicalize().unwrap_or(p)),
+                    insecure: cli.insecure.then_some(true),
+                    pin_sha256: cli.pin_sha256,
+                };
+                commands::profile::add(&path, &name, profile, default)
+            }
+            ProfileCommand::List => commands::profile::list(&path),
+            ProfileCommand::Remove { name } => commands::profile::remove(&path, &name),
+            ProfileCommand::SetDefault { name } => commands::profile::set_default(&path, &name),
+        };
+    }
+
+    // Precedence: CLI flag > environment variable (both handled by clap) >
+    // profile > built-in default
+    let settings = match &config_path {
+        Some(path) => Settings::load(path)?,
+        None => Settings::default(),
+    };
+    let profile = settings.profile(cli.profile.as_deref())?;
+
+    let url = cli
+        .url
+        .or(profile.url)
+        .unwrap_or_else(|| DEFAULT_URL.to_string());
+    let secret = match cli_secret.or(profile.secret) {
+        Some(secret) => Some(secret),
+        None => profile
+            .secret_file
+            .as_deref()
+            .map(settings::read_secret_file)
+            .transpose()?,
+    }
+    .filter(|s| !s.is_empty());
+
+    // TLS options on the command line replace conflicting ones from the
+    // profile: --ca-cert its pins, --pin-sha256 its CA and --insecure its pins
+    let (ca_cert, pins) = if cli.ca_cert.is_some() || !cli.pin_sha256.is_empty() {
+        (cli.ca_cert, cli.pin_sha256)
+    } else if cli.insecure {
+        (profile.ca_cert, Vec::new())
     } else {
-        Some(cli.secret)
+        (profile.ca_cert, profile.pin_sha256)
+    };
+
+    let defaults = ClientOptions::default();
+    let millis = Duration::from_millis;
+    let options = ClientOptions {
+        connect_timeout: cli
+            .connect_timeout
+            .or(profile.connect_timeout)
+            .map_or(defaults.connect_timeout, millis),
+        read_timeout: cli
+            .read_timeout
+            .or(profile.read_timeout)
+            .map_or(defaults.read_timeout, millis),
+        stream_timeout: cli
+            .stream_timeout
+            .or(profile.stream_timeout)
+            .map_or(defaults.stream_timeout, millis),
+        retries: cli.retries.or(profile.retries).unwrap_or(defaults.retries),
+        retry_backoff: cli
+            .retry_backoff
+            .or(profile.retry_backoff)
+            .map_or(defaults.retry_backoff, millis),
+        tls: TlsOptions {
+            ca_cert,
+            insecure: cli.insecure || profile.insecure.unwrap_or(false),
+            pins,
+        },
     };
 
-    let client = ClashClient::new(cli.url, secret);
+    let client = ClashClient::new(url, secret, options)?;
 
     match cli.command {
-        Command::Version => commands::version::execute(&client),
-        Command::Proxies => commands::proxies::execute(&client),
-        Command::Proxy { name } => commands::proxy::execute(&client, &name),
-        Command::Current => commands::current::execute(&client),
-        Command::Switch { group, proxy } => commands::switch::execute(&client, group, proxy),
-        Command::Mode { mode } => commands::mode::execute(&client, mode),
+        Command::Version => commands::version::execute(&client, format),
+        Command::Proxies => commands::proxies::execute(&client, format),
+        Command::Proxy { name } => commands::proxy::execute(&client, &name, format),
+        Command::Connections {
+            host,
+            chain,
+            rule,
+            close,
+        } => {
+            let filter = commands::connections::ConnectionFilter { host, chain, rule };
+            commands::connections::execute(&client, &filter, close)
+        }
+        Command::Current {
+            group: None,
+            all: false,
+        } => commands::current::execute(&client, format),
+        Command::Current { group, .. } => {
+            commands::current::execute_groups(&client, group.as_deref(), format)
+        }
+        Command::Status {
+            format: template,
+            watch,
+            interval,
+            waybar,
+        } => commands::status::execute(&client, &template, waybar, watch.then_some(interval)),
+        Command::Delay {
+            name,
+            test_url,
+            timeout,
+            expected,
+        } => {
+            let Some(name) = name.or(profile.group) else {
+                anyhow::bail!("No proxy or group given and the profile has no default group");
+            };
+            commands::delay::execute(
+                &client,
+                &name,
+                &test_url
+                    .or(profile.test_url)
+                    .unwrap_or_else(|| DEFAULT_TEST_URL.to_string()),
+                timeout.or(profile.timeout).unwrap_or(DEFAULT_TIMEOUT),
+                expected.as_deref(),
+            )
+        }
+        Command::Providers { action } => match action {
+            ProvidersCommand::List => commands::providers::list(&client),
+            ProvidersCommand::Show { name } => commands::providers::show(&client, &name),
+            ProvidersCommand::Update { name, .. } => {
+                commands::providers::update(&client, name.as_deref())
+            }
+            ProvidersCommand::Healthcheck { name, .. } => {
+                commands::providers::healthcheck(&client, name.as_deref())
+            }
+        },
+        Command::RuleProviders { action } => match action {
+            RuleProvidersCommand::List => commands::rule_providers::list(&client),
+            RuleProvidersCommand::Update { name, .. } => {
+                commands::rule_providers::update(&client, name.as_deref())
+            }
+        },
+        Command::Route { target } => commands::route::execute(&client, &target),
+        Command::Rules {
+            rule_type,
+            payload,
+            target,
+            count,
+        } => {
+            let filter = commands::rules::RuleFilter {
+                rule_type,
+                payload,
+                target,
+            };
+            commands::rules::execute(&client, &filter, count)
+        }
+        Command::Switch {
+            group,
+            proxy,
+            close_connections,
+            fastest,
+            exclude,
+            tolerance,
+            workers,
+            test_url,
+            timeout,
+        } => {
+            let group = group.or(profile.group);
+            let close_connections = close_connections
+                .or(profile.close_connections)
+                .unwrap_or(false);
+
+            if fastest {
+                let Some(group) = group else {
+                    anyhow::bail!("--fastest needs a group and the profile has no default group");
+                };
+                let options = commands::switch::FastestOptions {
+                    url: test_url
+                        .or(profile.test_url)
+                        .unwrap_or_else(|| DEFAULT_TEST_URL.to_string()),
+                    timeout: timeout.or(profile.timeout).unwrap_or(DEFAULT_TIMEOUT),
+                    exclude,
+                    tolerance,
+                    workers,
+                };
+                commands::switch::execute_fastest(
+                    &client,
+                    &group,
+                    &options,
+                    close_connections,
+                    format,
+                )
+            } else {
+                commands::switch::execute(&client, group, proxy, close_connections, format)
+            }
+        }
+        Command::Traffic { once, duration } => commands::traffic::execute(&client, once, duration),
+        Command::Logs {
+            level,
+            grep,
+            regex,
+            json,
+            no_reconnect,
+        } => {
+            let filter = commands::logs::LogFilter::new(grep, regex.as_deref())?;
+            commands::logs::execute(&client, level.as_str(), &filter, json, !no_reconnect)
+        }
+        Command::Tui {
+            test_url,
+            timeout,
+            refresh,
+        } => commands::tui::execute(
+            &client,
+            &commands::tui::TuiOptions {
+                test_url: test_url
+                    .or(profile.test_url)
+                    .unwrap_or_else(|| DEFAULT_TEST_URL.to_string()),
+                timeout: timeout.or(profile.timeout).unwrap_or(DEFAULT_TIMEOUT),
+                refresh: Duration::from_secs(refresh.max(1)),
+            },
+        ),
+        Command::Mode { mode } => commands::mode::execute(&client, mode, format),
+        Command::Profile { .. } => unreachable!("handled before connecting"),
     }
 }
@@ -1,13 +1,13 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct Version {
     pub meta: bool,
     pub version: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct Config {
     pub mode: String,
 }
@@ -17,10 +17,82 @@ pub struct ProxiesResponse {
     pub proxies: HashMap<String, ProxyInfo>,
 }
 
-#[derive(Deserialize, Debug)]
+/// The `type` of a proxy. Group types have their own variants; node
+/// protocols (Shadowsocks, Vmess, ...), Direct and Reject are kept as-is in
+/// `Unknown`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "String", into = "String")]
+pub enum ProxyType {
+    Selector,
+    URLTest,
+    Fallback,
+    LoadBalance,
+    Relay,
+    Unknown(String),
+}
+
+impl ProxyType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ProxyType::Selector => "Selector",
+            ProxyType::URLTest => "URLTest",
+            ProxyType::Fallback => "Fallback",
+            ProxyType::LoadBalance => "LoadBalance",
+            ProxyType::Relay => "Relay",
+            ProxyType::Unknown(name) => name,
+        }
+    }
+
+    /// Whether this is a proxy group rather than a single node
+    pub fn is_group(&self) -> bool {
+        !matches!(self, ProxyType::Unknown(_))
+    }
+
+    /// Whether the selected member can be changed through the API
+    pub fn is_selectable(&self) -> bool {
+        matches!(self, ProxyType::Selector)
+    }
+
+    /// Whether this is a built-in outbound that is not a proxy: `DIRECT`,
+    /// `REJECT`, `REJECT-DROP`, `PASS` or `COMPATIBLE`
+    pub fn is_builtin(&self) -> bool {
+        matches!(
+            self,
+            ProxyType::Unknown(name)
+                if matches!(name.as_str(), "Direct" | "Reject" | "RejectDrop" | "Pass" | "Compatible")
+        )
+    }
+}
+
+impl From<String> for ProxyType {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "Selector" => ProxyType::Selector,
+            "URLTest" => ProxyType::URLTest,
+            "Fallback" => ProxyType::Fallback,
+            "LoadBalance" => ProxyType::LoadBalance,
+            "Relay" => ProxyType::Relay,
+            _ => ProxyType::Unknown(name),
+        }
+    }
+}
+
+impl From<ProxyType> for String {
+    fn from(proxy_type: ProxyType) -> Self {
+        proxy_type.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for ProxyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
 pub struct ProxyInfo {
     #[serde(rename = "type")]
-    pub proxy_type: String,
+    pub proxy_type: ProxyType,
     #[serde(default)]
     pub all: Vec<String>,
     #[serde(default)]
@@ -29,12 +101,145 @@ pub struct ProxyInfo {
     pub history: Vec<HistoryItem>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct HistoryItem {
     pub time: String,
     pub delay: u32,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct DelayResponse {
+    pub delay: u32,
+}
+
+/// Body of a controller error response
+#[derive(Deserialize, Debug)]
+pub struct ErrorResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionsResponse {
+    #[serde(default)]
+    pub download_total: u64,
+    #[serde(default)]
+    pub upload_total: u64,
+    // Clash sends `null` rather than `[]` when there are no connections
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub connections: Vec<Connection>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection {
+    pub id: String,
+    pub metadata: ConnectionMetadata,
+    #[serde(default)]
+    pub upload: u64,
+    #[serde(default)]
+    pub download: u64,
+    #[serde(default)]
+    pub start: String,
+    #[serde(default)]
+    pub chains: Vec<String>,
+    #[serde(default)]
+    pub rule: String,
+    #[serde(default)]
+    pub rule_payload: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConnectionMetadata {
+    pub network: String,
+    #[serde(rename = "type")]
+    pub conn_type: String,
+    #[serde(rename = "sourceIP")]
+    pub source_ip: String,
+    pub source_port: String,
+    #[serde(rename = "destinationIP")]
+    pub destination_ip: String,
+    pub destination_port: String,
+    pub host: String,
+    pub process: String,
+    pub process_path: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Traffic {
+    pub up: u64,
+    pub down: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LogEntry {
+    #[serde(rename = "type")]
+    pub level: String,
+    pub payload: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RulesResponse {
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Rule {
+    #[serde(rename = "type")]
+    pub rule_type: String,
+    #[serde(default)]
+    pub payload: String,
+    pub proxy: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProxyProvidersResponse {
+    pub providers: HashMap<String, ProxyProvider>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyProvider {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    pub vehicle_type: String,
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub proxies: Vec<ProviderProxy>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProviderProxy {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub proxy_type: ProxyType,
+    #[serde(default)]
+    pub history: Vec<HistoryItem>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RuleProvidersResponse {
+    pub providers: HashMap<String, RuleProvider>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleProvider {
+    pub name: String,
+    pub behavior: String,
+    #[serde(default)]
+    pub format: String,
+    pub vehicle_type: String,
+    #[serde(default)]
+    pub rule_count: u64,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
 #[derive(Serialize)]
 pub struct SwitchRequest {
     pub name: String,
@@ -45,6 +250,14 @@ pub struct ModeRequest {
     pub mode: String,
 }
 
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,7 +278,7 @@ mod tests {
             "now": "DIRECT"
         }"#;
         let info: ProxyInfo = serde_json::from_str(json).unwrap();
-        assert_eq!(info.proxy_type, "Selector");
+        assert_eq!(info.proxy_type, ProxyType::Selector);
         assert_eq!(info.all.len(), 2);
         assert_eq!(info.now, "DIRECT");
     }
@@ -74,12 +287,33 @@ mod tests {
     fn test_proxy_info_defaults() {
         let json = r#"{"type": "Direct"}"#;
         let info: ProxyInfo = serde_json::from_str(json).unwrap();
-        assert_eq!(info.proxy_type, "Direct");
+        assert_eq!(info.proxy_type, ProxyType::Unknown("Direct".to_string()));
         assert!(info.all.is_empty());
         assert!(info.now.is_empty());
         assert!(info.history.is_empty());
     }
 
+    #[test]
+    fn test_proxy_type_roundtrip() {
+        for name in [
+            "Selector",
+            "URLTest",
+            "Fallback",
+            "LoadBalance",
+            "Relay",
+            "Vmess",
+        ] {
+            let json = format!("\"{}\"", name);
+            let proxy_type: ProxyType = serde_json::from_str(&json).unwrap();
+            assert_eq!(proxy_type.as_str(), name);
+            assert_eq!(serde_json::to_string(&proxy_type).unwrap(), json);
+        }
+        assert!(ProxyType::Fallback.is_group());
+        assert!(!ProxyType::Unknown("Shadowsocks".to_string()).is_group());
+        assert!(ProxyType::Selector.is_selectable());
+        assert!(!ProxyType::URLTest.is_selectable());
+    }
+
     #[test]
     fn test_config_deserialize() {
         let json = r#"{
@@ -125,6 +359,13 @@ mod tests {
         assert_eq!(item.delay, 150);
     }
 
+    #[test]
+    fn test_delay_response_deserialize() {
+        let json = r#"{"delay": 233}"#;
+        let response: DelayResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.delay, 233);
+    }
+
     #[test]
     fn test_proxies_response_deserialize() {
         let json = r#"{
@@ -144,4 +385,134 @@ mod tests {
         assert!(response.proxies.contains_key("DIRECT"));
         assert!(response.proxies.contains_key("GLOBAL"));
     }
+
+    #[test]
+    fn test_connections_response_deserialize() {
+        let json = r#"{
+            "downloadTotal": 2048,
+            "uploadTotal": 1024,
+            "connections": [{
+                "id": "5f0e5a1c",
+                "metadata": {
+                    "network": "tcp",
+                    "type": "HTTPS",
+                    "sourceIP": "127.0.0.1",
+                    "destinationIP": "142.250.1.1",
+                    "sourcePort": "53211",
+                    "destinationPort": "443",
+                    "host": "www.google.com",
+                    "process": "curl"
+                },
+                "upload": 512,
+                "download": 4096,
+                "start": "2024-01-01T00:00:00.000Z",
+                "chains": ["HK 01", "Proxy"],
+                "rule": "DomainSuffix",
+                "rulePayload": "google.com"
+            }]
+        }"#;
+        let response: ConnectionsResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.download_total, 2048);
+        assert_eq!(response.connections.len(), 1);
+        let conn = &response.connections[0];
+        assert_eq!(conn.metadata.host, "www.google.com");
+        assert_eq!(conn.metadata.destination_ip, "142.250.1.1");
+        assert_eq!(conn.chains, vec!["HK 01", "Proxy"]);
+        assert_eq!(conn.rule_payload, "google.com");
+        assert!(conn.metadata.process_path.is_empty());
+    }
+
+    #[test]
+    fn test_connections_response_null_connections() {
+        let json = r#"{"downloadTotal": 0, "uploadTotal": 0, "connections": null}"#;
+        let response: ConnectionsResponse = serde_json::from_str(json).unwrap();
+        assert!(response.connections.is_empty());
+    }
+
+    #[test]
+    fn test_traffic_deserialize() {
+        let json = r#"{"up": 1024, "down": 4096}"#;
+        let traffic: Traffic = serde_json::from_str(json).unwrap();
+        assert_eq!(traffic.up, 1024);
+        assert_eq!(traffic.down, 4096);
+    }
+
+    #[test]
+    fn test_log_entry_roundtrip() {
+        let json = r#"{"type":"info","payload":"[TCP] 127.0.0.1:5000 --> example.com:443"}"#;
+        let entry: LogEntry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry.level, "info");
+        assert!(entry.payload.contains("example.com"));
+        assert_eq!(serde_json::to_string(&entry).unwrap(), json);
+    }
+
+    #[test]
+    fn test_rules_response_deserialize() {
+        let json = r#"{
+            "rules": [
+                {"type": "DomainSuffix", "payload": "google.com", "proxy": "Proxy", "size": -1},
+                {"type": "Match", "payload": "", "proxy": "DIRECT"}
+            ]
+        }"#;
+        let response: RulesResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.rules.len(), 2);
+        assert_eq!(response.rules[0].rule_type, "DomainSuffix");
+        assert_eq!(response.rules[0].payload, "google.com");
+        assert_eq!(response.rules[1].proxy, "DIRECT");
+    }
+
+    #[test]
+    fn test_proxy_providers_response_deserialize() {
+        let json = r#"{
+            "providers": {
+                "default": {
+                    "name": "default",
+                    "type": "Proxy",
+                    "vehicleType": "Compatible",
+                    "proxies": null
+                },
+                "airport": {
+                    "name": "airport",
+                    "type": "Proxy",
+                    "vehicleType": "HTTP",
+                    "updatedAt": "2024-01-01T12:00:00.123+08:00",
+                    "proxies": [
+                        {"name": "HK 01", "type": "Shadowsocks", "history": [{"time": "2024-01-01T12:00:00Z", "delay": 88}]},
+                        {"name": "JP 02", "type": "Vmess"}
+                    ]
+                }
+            }
+        }"#;
+        let response: ProxyProvidersResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.providers.len(), 2);
+        assert!(response.providers["default"].proxies.is_empty());
+        let airport = &response.providers["airport"];
+        assert_eq!(airport.vehicle_type, "HTTP");
+        assert_eq!(airport.updated_at, "2024-01-01T12:00:00.123+08:00");
+        assert_eq!(airport.proxies.len(), 2);
+        assert_eq!(airport.proxies[0].history[0].delay, 88);
+    }
+
+    #[test]
+    fn test_rule_providers_response_deserialize() {
+        let json = r#"{
+            "providers": {
+                "reject": {
+                    "name": "reject",
+                    "type": "Rule",
+                    "behavior": "Domain",
+                    "format": "YamlRule",
+                    "vehicleType": "HTTP",
+                    "ruleCount": 1234,
+                    "updatedAt": "2024-02-01T08:00:00Z"
+                }
+            }
+        }"#;
+        let response: RuleProvidersResponse = serde_json::from_str(json).unwrap();
+        let provider = &response.providers["reject"];
+        assert_eq!(provider.behavior, "Domain");
+        assert_eq!(provider.format, "YamlRule");
+        assert_eq!(provider.rule_count, 1234);
+        assert_eq!(provider.updated_at, "2024-02-01T08:00:00Z");
+    }
 }
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// How command results are printed
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored, human-readable tables
+    #[default]
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// Uncolored, tab-separated lines for simple scripts
+    Plain,
+}
+
+/// A command result that can be printed in every `OutputFormat`
+pub trait Render: Serialize {
+    fn render_table(&self);
+    fn render_plain(&self);
+}
+
+pub fn print<T: Render>(value: &T, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => value.render_table(),
+        OutputFormat::Plain => value.render_plain(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Sample {
+        name: String,
+    }
+
+    impl Render for Sample {
+        fn render_table(&self) {}
+        fn render_plain(&self) {}
+    }
+
+    #[test]
+    fn test_print_json() {
+        let sample = Sample {
+            name: "GLOBAL".to_string(),
+        };
+        assert!(print(&sample, OutputFormat::Json).is_ok());
+        assert_eq!(
+            serde_json::to_string(&sample).unwrap(),
+            r#"{"name":"GLOBAL"}"#
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_URL: &str = "http://localhost:9090";
+pub const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+pub const DEFAULT_TIMEOUT: u32 = 5000;
+
+/// Contents of `config.toml`
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Settings {
+    /// Profile used when `--profile` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named controller with its per-controller defaults
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// File holding the secret, used when `secret` is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_file: Option<PathBuf>,
+    /// Group used by `switch` and `delay` when none is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Latency test timeout in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+    /// Latency test URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_url: Option<String>,
+    /// Close connections through the group after switching
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub close_connections: Option<bool>,
+    /// Controller connect timeout in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+    /// Controller response timeout in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_timeout: Option<u64>,
+    /// Idle timeout of the traffic stream in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_timeout: Option<u64>,
+    /// Retries for GET requests that got no response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// Wait before the first retry in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_backoff: Option<u64>,
+    /// PEM file with the CA that signed an HTTPS controller's certificate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<PathBuf>,
+    /// Skip verification of an HTTPS controller's certificate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insecure: Option<bool>,
+    /// SHA-256 fingerprints of the accepted controller certificates
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pin_sha256: Vec<String>,
+}
+
+impl Settings {
+    /// Default location: `$XDG_CONFIG_HOME/clash-switcher/config.toml`,
+    /// falling back to `~/.config/clash-switcher/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .or_else(|| std::env::var_os("USERPROFILE"))
+                    .map(|home| PathBuf::from(home).join(".config"))
+            })?;
+        Some(base.join("clash-switcher").join("config.toml"))
+    }
+
+    /// Load settings from `path`. A missing file yields empty settings.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let content = toml::to_string_pretty(self).context("Failed to serialize config")?;
+        let write_error = || format!("Failed to write config file {}", path.display());
+
+        // Profiles may hold secrets: create the file as 0600 and tighten an
+        // existing file before any content is written
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path).with_context(write_error)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+        }
+        file.write_all(content.as_bytes())
+            .with_context(write_error)?;
+
+        Ok(())
+    }
+
+    /// The profile selected by `name`, or the default profile. Returns an
+    /// empty profile when neither is set.
+    pub fn profile(&self, name: Option<&str>) -> anyhow::Result<Profile> {
+        let Some(name) = name.or(self.default.as_deref()) else {
+            return Ok(Profile::default());
+        };
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.clone()),
+            None => anyhow::bail!(
+                "Profile '{}' not found. Use 'profile list' to see available profiles.",
+                name
+            ),
+        }
+    }
+}
+
+/// Read an API secret from `path`, ignoring surrounding whitespace. The file
+/// must not be readable by group or others.
+pub fn read_secret_file(path: &Path) -> anyhow::Result<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path)
+            .with_context(|| format!("Failed to read secret file {}", path.display()))?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            anyhow::bail!(
+                "Refusing to use secret file {}: it is accessible by group or others (mode {:o}). Run 'chmod 600 {}'.",
+                path.display(),
+                mode & 0o777,
+                path.display()
+            );
+        }
+    }
+
+    let secret = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read secret file {}", path.display()))?;
+    Ok(secret.trim().to_string())
+}
+
+/// Read an API secret from the first line of standard input
+pub fn read_secret_stdin() -> anyhow::Result<String> {
+    let mut secret = String::new();
+    std::io::stdin()
+        .read_line(&mut secret)
+        .context("Failed to read secret from stdin")?;
+    Ok(secret.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!(
+                "clash-switcher-test-{}-{}",
+                std::process::id(),
+                name
+            ))
+            .join("config.toml")
+    }
+
+    #[test]
+    fn test_settings_parse() {
+        let content = r#"
+            default = "router"
+
+            [profiles.router]
+            url = "http://192.168.1.1:9090"
+            secret = "s3cret"
+            group = "Proxy"
+            timeout = 3000
+            close-connections = true
+            read-timeout = 2000
+            retries = 0
+            pin-sha256 = ["AB:CD"]
+
+            [profiles.laptop]
+            url = "http://localhost:9090"
+        "#;
+        let settings: Settings = toml::from_str(content).unwrap();
+        assert_eq!(settings.default.as_deref(), Some("router"));
+        assert_eq!(settings.profiles.len(), 2);
+        let router = &settings.profiles["router"];
+        assert_eq!(router.secret.as_deref(), Some("s3cret"));
+        assert_eq!(router.timeout, Some(3000));
+        assert_eq!(router.close_connections, Some(true));
+        assert_eq!(router.read_timeout, Some(2000));
+        assert_eq!(router.retries, Some(0));
+        assert!(router.connect_timeout.is_none());
+        assert_eq!(router.pin_sha256, vec!["AB:CD"]);
+        assert!(settings.profiles["laptop"].pin_sha256.is_empty());
+        assert!(settings.profiles["laptop"].group.is_none());
+    }
+
+    #[test]
+    fn test_settings_profile_selection() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.profile(None).unwrap(), Profile::default());
+        assert!(settings.profile(Some("missing")).is_err());
+
+        settings.profiles.insert(
+            "home".to_string(),
+            Profile {
+                url: Some("http://home:9090".to_string()),
+                ..Default::default()
+            },
+        );
+        settings.default = Some("home".to_string());
+        assert_eq!(
+            settings.profile(None).unwrap().url.as_deref(),
+            Some("http://home:9090")
+        );
+    }
+
+    #[test]
+    fn test_settings_save_and_load() {
+        let path = temp_path("roundtrip");
+        let mut settings = Settings::default();
+        settings.profiles.insert(
+            "router".to_string(),
+            Profile {
+                url: Some("http://192.168.1.1:9090".to_string()),
+                secret: Some("s3cret".to_string()),
+                ..Default::default()
+            },
+        );
+        settings.default = Some("router".to_string());
+
+        settings.save(&path).unwrap();
+        assert_eq!(Settings::load(&path).unwrap(), settings);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_settings_load_missing_file() {
+        let path = temp_path("missing");
+        assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_secret_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("secret").with_file_name("secret");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "s3cret\n").unwrap();
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(read_secret_file(&path).unwrap(), "s3cret");
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(read_secret_file(&path).is_err());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_read_secret_file_missing() {
+        let path = temp_path("missing-secret").with_file_name("secret");
+        assert!(read_secret_file(&path).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::error::{ClashError, Result};
+use reqwest::blocking::ClientBuilder;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, OtherError, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// How to verify an HTTPS controller's certificate
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsOptions {
+    /// PEM file with extra trusted CA certificates
+    pub ca_cert: Option<PathBuf>,
+    /// Accept any certificate
+    pub insecure: bool,
+    /// SHA-256 fingerprints of accepted server certificates. When set, only
+    /// these certificates are accepted and the CA chain is not checked, so
+    /// `ca_cert` must not be set as well.
+    pub pins: Vec<String>,
+}
+
+impl TlsOptions {
+    /// Configure `builder` for these options
+    pub fn apply(&self, builder: ClientBuilder) -> Result<ClientBuilder> {
+        if !self.pins.is_empty() && self.ca_cert.is_some() {
+            return Err(ClashError::InvalidRequest(
+                "--ca-cert and --pin-sha256 cannot be combined, a pinned certificate is accepted whoever issued it".to_string(),
+            ));
+        }
+
+        if !self.pins.is_empty() {
+            let pins = self
+                .pins
+                .iter()
+                .map(|pin| parse_fingerprint(pin))
+                .collect::<Result<Vec<_>>>()?;
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+            let config = rustls::ClientConfig::builder_with_provider(provider.clone())
+                .with_safe_default_protocol_versions()
+                .map_err(|e| ClashError::InvalidRequest(format!("TLS setup failed: {}", e)))?
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+                    pins,
+                    algorithms: provider.signature_verification_algorithms,
+                }))
+                .with_no_client_auth();
+            return Ok(builder.use_preconfigured_tls(config));
+        }
+
+        let mut builder = builder.danger_accept_invalid_certs(self.insecure);
+        if let Some(path) = &self.ca_cert {
+            let pem = fs::read(path).map_err(|e| {
+                ClashError::InvalidRequest(format!(
+                    "Failed to read CA certificate {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| {
+                ClashError::InvalidRequest(format!(
+                    "Invalid CA certificate {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            if certs.is_empty() {
+                return Err(ClashError::InvalidRequest(format!(
+                    "No certificate found in {}",
+                    path.display()
+                )));
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        Ok(builder)
+    }
+}
+
+/// SHA-256 fingerprint of a DER certificate as colon-separated uppercase hex,
+/// the format printed by `openssl x509 -fingerprint -sha256`
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Parse a fingerprint given as hex, with or without colons
+fn parse_fingerprint(pin: &str) -> Result<[u8; 32]> {
+    let hex: String = pin.chars().filter(|c| *c != ':').collect();
+    let invalid = || {
+        ClashError::InvalidRequest(format!(
+            "Invalid --pin-sha256 '{}', expected 64 hex digits",
+            pin
+        ))
+    };
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+/// Why the server certificate was rejected, if that is why a request failed
+pub fn certificate_error(error: &(dyn std::error::Error + 'static)) -> Option<String> {
+    let mut cause = Some(error);
+    while let Some(e) = cause {
+        match e.downcast_ref::<rustls::Error>() {
+            Some(rustls::Error::InvalidCertificate(CertificateError::Other(OtherError(other)))) => {
+                return Some(other.to_string());
+            }
+            Some(rustls::Error::InvalidCertificate(reason)) => {
+                return Some(format!("{:?}", reason));
+            }
+            _ => {}
+        }
+        // io::Error::source skips the error it wraps, so descend explicitly
+        cause = match e
+            .downcast_ref::<std::io::Error>()
+            .and_then(|io| io.get_ref())
+        {
+            Some(inner) => Some(inner),
+            None => e.source(),
+        };
+    }
+    None
+}
+
+#[derive(Debug)]
+struct PinMismatch(String);
+
+impl fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "certificate fingerprint {} matches no --pin-sha256",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for PinMismatch {}
+
+/// Accepts exactly the pinned certificates, whoever issued them
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pins: Vec<[u8; 32]>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let digest: [u8; 32] = Sha256::digest(end_entity.as_ref()).into();
+        if self.pins.contains(&digest) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+                OtherError(Arc::new(PinMismatch(fingerprint(end_entity.as_ref())))),
+            )))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fingerprint() {
+        let hex = "AB".repeat(32);
+        assert_eq!(parse_fingerprint(&hex).unwrap(), [0xAB; 32]);
+
+        let colons = vec!["ab"; 32].join(":");
+        assert_eq!(parse_fingerprint(&colons).unwrap(), [0xAB; 32]);
+
+        assert!(parse_fingerprint("abcd").is_err());
+        assert!(parse_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_round_trip() {
+        let printed = fingerprint(b"certificate");
+        assert_eq!(printed.len(), 32 * 3 - 1);
+        assert_eq!(
+            parse_fingerprint(&printed).unwrap(),
+            <[u8; 32]>::from(Sha256::digest(b"certificate"))
+        );
+    }
+}
@@ -10,6 +10,145 @@ pub fn pad_string(s: &str, width: usize) -> String {
     }
 }
 
+/// Format a byte count with binary units (e.g. `1.5 MB`)
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Format a per-second byte rate (e.g. `1.5 MB/s`)
+pub fn format_rate(bytes_per_sec: u64) -> String {
+    format!("{}/s", format_bytes(bytes_per_sec))
+}
+
+/// Shorten an RFC 3339 timestamp to `YYYY-MM-DD HH:MM:SS`, dropping
+/// fractional seconds and the offset
+pub fn format_timestamp(timestamp: &str) -> String {
+    match timestamp.get(..19) {
+        Some(prefix) if timestamp.as_bytes().get(10) == Some(&b'T') => prefix.replacen('T', " ", 1),
+        _ => timestamp.to_string(),
+    }
+}
+
+/// Case-insensitive subsequence match. Higher scores are better matches:
+/// consecutive characters and matches at word starts score higher.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<i32> {
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut matched = 0;
+    let mut previous: Option<usize> = None;
+    let mut prev_char: Option<char> = None;
+
+    for (idx, c) in candidate.to_lowercase().chars().enumerate() {
+        if matched < query.len() && c == query[matched] {
+            score += 1;
+            if previous.is_some_and(|p| p + 1 == idx) {
+                score += 5;
+            }
+            if prev_char.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            if let Some(p) = previous {
+                score -= (idx - p - 1).min(3) as i32;
+            }
+            previous = Some(idx);
+            matched += 1;
+        }
+        prev_char = Some(c);
+    }
+
+    (matched == query.len()).then_some(score)
+}
+
+/// Lowercase `name`, drop emoji, flags and other symbols, and collapse
+/// whitespace, so "🇯🇵 Japan 03 | IPLC" compares as "japan 03 | iplc"
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_ascii_punctuation() || c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Result of looking up a user-supplied name among candidates
+#[derive(Debug, PartialEq)]
+pub enum NameMatch<'a> {
+    Unique(&'a str),
+    Ambiguous(Vec<&'a str>),
+    NotFound,
+}
+
+/// Find `query` among `candidates`, trying in order: the exact name, a
+/// 1-based index, then case-insensitive equality, prefix, substring and
+/// subsequence matches on normalized names. The first step with any match
+/// decides the result.
+pub fn match_name<'a>(query: &str, candidates: &'a [String]) -> NameMatch<'a> {
+    if let Some(exact) = candidates.iter().find(|c| *c == query) {
+        return NameMatch::Unique(exact);
+    }
+    if let Ok(idx) = query.trim().parse::<usize>()
+        && (1..=candidates.len()).contains(&idx)
+    {
+        return NameMatch::Unique(&candidates[idx - 1]);
+    }
+
+    let query = normalize_name(query);
+    if query.is_empty() {
+        return NameMatch::NotFound;
+    }
+    let normalized: Vec<(String, &str)> = candidates
+        .iter()
+        .map(|c| (normalize_name(c), c.as_str()))
+        .collect();
+
+    let steps: [&dyn Fn(&str) -> bool; 3] = [
+        &|name| name == query,
+        &|name| name.starts_with(&query),
+        &|name| name.contains(&query),
+    ];
+    for step in steps {
+        let matches: Vec<&str> = normalized
+            .iter()
+            .filter(|(name, _)| step(name))
+            .map(|(_, original)| *original)
+            .collect();
+        match matches.len() {
+            0 => continue,
+            1 => return NameMatch::Unique(matches[0]),
+            _ => return NameMatch::Ambiguous(matches),
+        }
+    }
+
+    let mut scored: Vec<(i32, &str)> = normalized
+        .iter()
+        .filter_map(|(name, original)| fuzzy_match(&query, name).map(|score| (score, *original)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    match scored.len() {
+        0 => NameMatch::NotFound,
+        1 => NameMatch::Unique(scored[0].1),
+        _ => NameMatch::Ambiguous(scored.into_iter().map(|(_, name)| name).collect()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,4 +197,87 @@ mod tests {
         // Korean characters (2 width each)
         assert_eq!(pad_string("한글", 6), "한글  ");
     }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KB");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_format_rate() {
+        assert_eq!(format_rate(0), "0 B/s");
+        assert_eq!(format_rate(2048), "2.0 KB/s");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(
+            format_timestamp("2024-01-01T12:34:56.789+08:00"),
+            "2024-01-01 12:34:56"
+        );
+        assert_eq!(
+            format_timestamp("2024-01-01T12:34:56Z"),
+            "2024-01-01 12:34:56"
+        );
+        assert_eq!(format_timestamp(""), "");
+        assert_eq!(format_timestamp("never"), "never");
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("jp", "JP Tokyo 02").is_some());
+        assert!(fuzzy_match("tk02", "JP Tokyo 02").is_some());
+        assert!(fuzzy_match("xyz", "JP Tokyo 02").is_none());
+        assert_eq!(fuzzy_match("", "anything"), Some(0));
+        // Consecutive matches beat scattered ones
+        assert!(fuzzy_match("hk", "HK 01").unwrap() > fuzzy_match("hk", "Hong Kong").unwrap());
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(
+            normalize_name("🇯🇵 Japan 03 | IPLC x1.5"),
+            "japan 03 | iplc x1.5"
+        );
+        assert_eq!(normalize_name("  HK\u{fe0f}  01 "), "hk 01");
+        assert_eq!(normalize_name("香港 01"), "香港 01");
+    }
+
+    #[test]
+    fn test_match_name() {
+        let names: Vec<String> = [
+            "🇯🇵 Japan 03 | IPLC x1.5",
+            "🇯🇵 Japan 04",
+            "🇭🇰 HK 01",
+            "DIRECT",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(match_name("DIRECT", &names), NameMatch::Unique("DIRECT"));
+        assert_eq!(match_name("direct", &names), NameMatch::Unique("DIRECT"));
+        assert_eq!(match_name("3", &names), NameMatch::Unique("🇭🇰 HK 01"));
+        assert_eq!(
+            match_name("japan 03", &names),
+            NameMatch::Unique(names[0].as_str())
+        );
+        assert_eq!(
+            match_name("iplc", &names),
+            NameMatch::Unique(names[0].as_str())
+        );
+        assert_eq!(match_name("jp04", &names), NameMatch::Unique("🇯🇵 Japan 04"));
+        assert_eq!(
+            match_name("japan", &names),
+            NameMatch::Ambiguous(vec![names[0].as_str(), names[1].as_str()])
+        );
+        assert_eq!(match_name("zz", &names), NameMatch::NotFound);
+        assert_eq!(match_name("9", &names), NameMatch::NotFound);
+        assert_eq!(match_name("🇯🇵", &names), NameMatch::NotFound);
+    }
 }